@@ -0,0 +1,1187 @@
+use chrono::{NaiveDate, NaiveTime, TimeDelta};
+use csv::{ReaderBuilder, Trim};
+use log::{debug, info};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::enums::{
+    KdiDirectionEnum, KdiExceptionEnum, KdiFareEnum, KdiParkingStopEnum, KdiSupportedEnum,
+    KdiTransportEnum,
+};
+use crate::error::{KdiError, Record};
+use crate::kml::Placemark;
+use crate::rejection::Rejections;
+use crate::source::{BikeSharingSource, FareSource, GtfsSource, KmlSource};
+use crate::structs::{
+    KdiAgency, KdiBikeSharingStop, KdiCalendar, KdiCalendarException, KdiFare, KdiFareRule,
+    KdiLocation, KdiParkingStop, KdiPublicTransportStop, KdiRoute, KdiStopTime, KdiTrip,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TT {
+    Urban,
+    ExtraUrban,
+}
+
+impl Display for TT {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TT::ExtraUrban => write!(f, "EU"),
+            TT::Urban => write!(f, "U"),
+        }
+    }
+}
+
+fn to_correct_id(tt: &TT, id: &str) -> String {
+    format!("{}_{}", tt, id)
+}
+
+fn kml_id(source: &KmlSource, index: usize) -> String {
+    format!("{}_{}", source.prefix, index)
+}
+
+fn kml_record(source: &KmlSource, index: usize) -> Record {
+    Record::new(&source.path, Some(index), Some(&kml_id(source, index)))
+}
+
+/// `(latitude, longitude)` of a placemark, from its `longitude,latitude[,altitude]` coordinates.
+/// The altitude is dropped
+fn kml_coordinate(
+    source: &KmlSource,
+    index: usize,
+    placemark: &Placemark,
+) -> Result<(f64, f64), KdiError> {
+    let malformed = || KdiError::MalformedCoordinate {
+        record: kml_record(source, index),
+        value: placemark.point.coordinates.clone(),
+    };
+    let coordinate = placemark
+        .point
+        .coordinates
+        .trim()
+        .split(',')
+        .map(|c| c.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| malformed())?;
+    if !(2..=3).contains(&coordinate.len()) {
+        return Err(malformed());
+    }
+
+    Ok((coordinate[1], coordinate[0]))
+}
+
+/// Value of the `name` `SimpleData` of a placemark
+fn kml_data<'a>(
+    source: &KmlSource,
+    index: usize,
+    placemark: &'a Placemark,
+    name: &'static str,
+) -> Result<&'a str, KdiError> {
+    placemark
+        .extended_data
+        .schema_data
+        .simple_datas
+        .iter()
+        .find(|d| d.name == name)
+        .map(|d| d.value.as_str())
+        .ok_or_else(|| KdiError::MissingField {
+            record: kml_record(source, index),
+            field: name,
+        })
+}
+
+/// Parsed value of the `name` `SimpleData` of a placemark
+fn kml_data_parse<T: FromStr>(
+    source: &KmlSource,
+    index: usize,
+    placemark: &Placemark,
+    name: &'static str,
+) -> Result<T, KdiError> {
+    let value = kml_data(source, index, placemark, name)?;
+    value.parse().map_err(|_| KdiError::InvalidField {
+        record: kml_record(source, index),
+        field: name,
+        value: value.to_string(),
+    })
+}
+
+/// `(latitude, longitude)` of a bike sharing station
+fn bike_sharing_coordinate(
+    source: &BikeSharingSource,
+    index: usize,
+) -> Result<(f64, f64), KdiError> {
+    let bs = &source.bike_sharing[index];
+    if bs.position.len() != 2 {
+        return Err(KdiError::MalformedCoordinate {
+            record: Record::new(&source.path, Some(index), Some(&bs.id)),
+            value: format!("{:?}", bs.position),
+        });
+    }
+
+    Ok((bs.position[0], bs.position[1]))
+}
+
+fn gtfs_record(source: &GtfsSource, id: &str) -> Record {
+    Record::new(&source.path, None, Some(id))
+}
+
+/// Deserialize every row of the `name` `CSV` entry of a fare archive
+fn fare_entry<T: DeserializeOwned>(
+    source: &FareSource,
+    name: &str,
+    entity: &'static str,
+    rejections: &mut Rejections,
+) -> Result<Vec<T>, KdiError> {
+    let content = source.read_entry(&mut source.archive()?, name)?;
+    let mut rows: Vec<T> = Vec::new();
+
+    for result in ReaderBuilder::new()
+        .trim(Trim::Headers)
+        .from_reader(content.as_bytes())
+        .deserialize()
+    {
+        let row = result.map_err(|error| KdiError::Csv {
+            file: source.path.join(name),
+            source: error,
+        });
+        if let Some(row) = rejections.check(entity, row)? {
+            rows.push(row);
+        }
+    }
+
+    Ok(rows)
+}
+
+/// `zones_*.txt` row
+#[derive(Deserialize)]
+struct FareZone {
+    #[serde(rename(deserialize = "ZONE_ID"))]
+    pub id: String,
+    #[serde(rename(deserialize = "ZONE_NAME"))]
+    pub name: String,
+    #[serde(rename(deserialize = "ZONE_LAT"))]
+    pub latitude: f64,
+    #[serde(rename(deserialize = "ZONE_LON"))]
+    pub longitude: f64,
+}
+
+fn zones_entry(tt: TT) -> &'static str {
+    if matches!(tt, TT::ExtraUrban) {
+        "tariffegtfsextraurbano/zones_extraurbano.txt"
+    } else {
+        "tariffegtfsurbano/zones_urbano.txt"
+    }
+}
+
+pub fn align_location_zone(
+    source: &FareSource,
+    locations: &mut Vec<KdiLocation>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    let tt = source.tt;
+    let zones: Vec<FareZone> = fare_entry(source, zones_entry(tt), "Common::Location", rejections)?;
+
+    for zone in zones {
+        locations.push(KdiLocation {
+            id: format!("ZONE_{}", to_correct_id(&tt, &zone.id)),
+            name: zone.name,
+            latitude: zone.latitude,
+            longitude: zone.longitude,
+        });
+    }
+
+    locations.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_location_public_transport_stop(
+    source: &GtfsSource,
+    locations: &mut Vec<KdiLocation>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for stop in source.gtfs.stops.values() {
+        let location = || -> Result<KdiLocation, KdiError> {
+            Ok(KdiLocation {
+                id: to_correct_id(&source.tt, &stop.id),
+                name: stop.name.clone(),
+                latitude: stop.latitude.ok_or_else(|| KdiError::MissingField {
+                    record: gtfs_record(source, &stop.id),
+                    field: "stop_lat",
+                })?,
+                longitude: stop.longitude.ok_or_else(|| KdiError::MissingField {
+                    record: gtfs_record(source, &stop.id),
+                    field: "stop_lon",
+                })?,
+            })
+        };
+        if let Some(location) = rejections.check("Common::Location", location())? {
+            locations.push(location);
+        }
+    }
+
+    locations.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_location_car_sharing(
+    source: &KmlSource,
+    locations: &mut Vec<KdiLocation>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let location = || -> Result<KdiLocation, KdiError> {
+            let (latitude, longitude) = kml_coordinate(source, i, placemark)?;
+            Ok(KdiLocation {
+                id: kml_id(source, i),
+                name: kml_data(source, i, placemark, "nomepos")?.to_string(),
+                latitude,
+                longitude,
+            })
+        };
+        if let Some(location) = rejections.check("Common::Location", location())? {
+            locations.push(location);
+        }
+    }
+
+    locations.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_location_centro_in_bici(
+    source: &KmlSource,
+    locations: &mut Vec<KdiLocation>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let location = || -> Result<KdiLocation, KdiError> {
+            let (latitude, longitude) = kml_coordinate(source, i, placemark)?;
+            Ok(KdiLocation {
+                id: kml_id(source, i),
+                name: kml_data(source, i, placemark, "desc")?.to_string(),
+                latitude,
+                longitude,
+            })
+        };
+        if let Some(location) = rejections.check("Common::Location", location())? {
+            locations.push(location);
+        }
+    }
+
+    locations.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_location_parcheggio_protetto_biciclette(
+    source: &KmlSource,
+    locations: &mut Vec<KdiLocation>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let location = || -> Result<KdiLocation, KdiError> {
+            let (latitude, longitude) = kml_coordinate(source, i, placemark)?;
+            Ok(KdiLocation {
+                id: kml_id(source, i),
+                name: kml_data(source, i, placemark, "park")?.to_string(),
+                latitude,
+                longitude,
+            })
+        };
+        if let Some(location) = rejections.check("Common::Location", location())? {
+            locations.push(location);
+        }
+    }
+
+    locations.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_location_taxi(
+    source: &KmlSource,
+    locations: &mut Vec<KdiLocation>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let location = || -> Result<KdiLocation, KdiError> {
+            let (latitude, longitude) = kml_coordinate(source, i, placemark)?;
+            Ok(KdiLocation {
+                id: kml_id(source, i),
+                name: kml_data(source, i, placemark, "nome")?.to_string(),
+                latitude,
+                longitude,
+            })
+        };
+        if let Some(location) = rejections.check("Common::Location", location())? {
+            locations.push(location);
+        }
+    }
+
+    locations.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_location_bike_sharing(
+    source: &BikeSharingSource,
+    locations: &mut Vec<KdiLocation>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, bs) in source.bike_sharing.iter().enumerate() {
+        let location =
+            bike_sharing_coordinate(source, i).map(|(latitude, longitude)| KdiLocation {
+                id: format!("{}_{}", source.prefix, bs.id),
+                name: bs.name.clone(),
+                latitude,
+                longitude,
+            });
+        if let Some(location) = rejections.check("Common::Location", location)? {
+            locations.push(location);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn align_calendar_exception(
+    source: &GtfsSource,
+    calendar_exceptions: &mut Vec<KdiCalendarException>,
+) -> Result<(), KdiError> {
+    let mut index: usize = calendar_exceptions.len();
+
+    // Sorted services, for stable IDs across runs
+    let mut service_ids: Vec<&String> = source.gtfs.calendar_dates.keys().collect();
+    service_ids.sort();
+
+    for service_id in service_ids {
+        for cd in &source.gtfs.calendar_dates[service_id] {
+            calendar_exceptions.push(KdiCalendarException {
+                id: index.to_string(),
+                calendar: to_correct_id(&source.tt, &cd.service_id),
+                date: cd
+                    .date
+                    .and_time(NaiveTime::MIN)
+                    .format("%Y-%m-%dT%H:%M:%S")
+                    .to_string(),
+                exception: KdiExceptionEnum::from(cd.exception_type),
+            });
+
+            index += 1;
+        }
+    }
+
+    calendar_exceptions.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_calendar(
+    source: &GtfsSource,
+    calendars: &mut Vec<KdiCalendar>,
+) -> Result<(), KdiError> {
+    for calendar in source.gtfs.calendar.values() {
+        calendars.push(KdiCalendar {
+            id: to_correct_id(&source.tt, &calendar.id),
+            start_date: calendar
+                .start_date
+                .and_time(NaiveTime::MIN)
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string(),
+            end_date: calendar
+                .end_date
+                .and_time(NaiveTime::MIN)
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string(),
+            monday: calendar.monday,
+            tuesday: calendar.tuesday,
+            wednesday: calendar.wednesday,
+            thursday: calendar.thursday,
+            friday: calendar.friday,
+            saturday: calendar.saturday,
+            sunday: calendar.sunday,
+        });
+    }
+
+    calendars.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_agency<'a>(
+    source: &'a GtfsSource,
+    agencies: &mut Vec<KdiAgency<'a>>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    if source.gtfs.agencies.len() != 1 {
+        return Err(KdiError::AgencyCount {
+            file: source.path.clone(),
+            count: source.gtfs.agencies.len(),
+        });
+    }
+    let gtfs_agency = &source.gtfs.agencies[0];
+    let agency = || -> Result<KdiAgency<'a>, KdiError> {
+        let id = gtfs_agency
+            .id
+            .as_ref()
+            .ok_or_else(|| KdiError::MissingField {
+                record: Record::new(&source.path, Some(0), None),
+                field: "agency_id",
+            })?;
+        Ok(KdiAgency {
+            id,
+            name: &gtfs_agency.name,
+            email: "info@trentinotrasporti.it",
+            phone: gtfs_agency
+                .phone
+                .as_ref()
+                .ok_or_else(|| KdiError::MissingField {
+                    record: gtfs_record(source, id),
+                    field: "agency_phone",
+                })?,
+            url: &gtfs_agency.url,
+            timezone: &gtfs_agency.timezone,
+        })
+    };
+    if let Some(agency) = rejections.check("Common::Agency", agency())? {
+        // Same agency shared by multiple feeds
+        if !agencies.iter().any(|a| a.id == agency.id) {
+            agencies.push(agency);
+        }
+    }
+
+    Ok(())
+}
+
+/// Stream the fare rules of `sources` to `f`, in source and entry order.
+/// Fare rule entries are read row by row straight from their archive, every fare rule is passed
+/// to `f` with the path of its entry.
+///
+/// Zones are looked up in the zones of the fare rule network first, then in the zones of the other
+/// networks: urban fare rules may contain extraurban zones. Invalid zone rows are rejected
+/// as `Common::Location` records and fare rules with an unknown zone as dangling references
+pub fn align_fare_rule(
+    sources: &[FareSource],
+    rejections: &mut Rejections,
+    mut f: impl FnMut(&Path, KdiFareRule) -> Result<(), KdiError>,
+) -> Result<(), KdiError> {
+    let mut zones = FareZones::default();
+    for source in sources {
+        let ids = fare_entry::<FareZone>(
+            source,
+            zones_entry(source.tt),
+            "Common::Location",
+            rejections,
+        )?
+        .into_iter()
+        .map(|zone| zone.id)
+        .collect();
+        zones.zones.push((source.tt, ids));
+    }
+
+    for source in sources {
+        let tt = source.tt;
+        let names = if matches!(tt, TT::ExtraUrban) {
+            [
+                "tariffegtfsextraurbano/fare_rules_extraurbano.txt",
+                "tariffegtfsextraurbano/fare_rules_extraurbano_cartascalare.txt",
+                "tariffegtfsextraurbano/fare_rules_extraurbano_mobile.txt",
+            ]
+        } else {
+            [
+                "tariffegtfsurbano/fare_rules_urbano.txt",
+                "tariffegtfsurbano/fare_rules_urbano_cartascalare.txt",
+                "tariffegtfsurbano/fare_rules_urbano_mobile.txt",
+            ]
+        };
+
+        let mut archive = source.archive()?;
+        for name in names {
+            let entry = source.path.join(name);
+            let file = archive.by_name(name).map_err(|error| KdiError::Zip {
+                file: entry.clone(),
+                source: error,
+            })?;
+            for (index, result) in ReaderBuilder::new()
+                .trim(Trim::Headers)
+                .from_reader(file)
+                .into_deserialize()
+                .enumerate()
+            {
+                let row = result.map_err(|error| KdiError::Csv {
+                    file: entry.clone(),
+                    source: error,
+                });
+                // The header is record 0, as in the `CSV` errors
+                let record = || Record::new(&entry, Some(index + 1), None);
+                let row = row.and_then(|fare_rule: KdiFareRule| {
+                    let mut zone = |id, field| zones.location(tt, id, field, record);
+                    Ok(KdiFareRule {
+                        id: String::new(),
+                        fare: to_correct_id(&tt, &fare_rule.fare),
+                        route: fare_rule.route.map(|route| to_correct_id(&tt, &route)),
+                        origin: fare_rule.origin.map(|id| zone(id, "origin")).transpose()?,
+                        destination: fare_rule
+                            .destination
+                            .map(|id| zone(id, "destination"))
+                            .transpose()?,
+                        contains: fare_rule
+                            .contains
+                            .map(|id| zone(id, "contains"))
+                            .transpose()?,
+                    })
+                });
+                if let Some(mut fare_rule) = rejections.check("Core::FareRule", row)? {
+                    fare_rule.id = fare_rule_id(&fare_rule);
+                    f(&entry, fare_rule)?;
+                }
+            }
+        }
+    }
+    if !zones.fallbacks.is_empty() {
+        info!(
+            "{} fare rule zones found in the zones of another network",
+            zones.fallbacks.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Zone IDs of the fare sources, by network
+#[derive(Default)]
+struct FareZones {
+    zones: Vec<(TT, HashSet<String>)>,
+    /// Zones of a network found in the zones of another network
+    fallbacks: HashSet<(TT, String)>,
+}
+
+impl FareZones {
+    /// `ZONE_<network>_<id>` location of the `field` zone of a fare rule of the `tt` network
+    fn location(
+        &mut self,
+        tt: TT,
+        id: String,
+        field: &'static str,
+        record: impl FnOnce() -> Record,
+    ) -> Result<String, KdiError> {
+        let own = self
+            .zones
+            .iter()
+            .any(|(zone_tt, ids)| *zone_tt == tt && ids.contains(&id));
+        let zone_tt = if own {
+            tt
+        } else {
+            match self.zones.iter().find(|(_, ids)| ids.contains(&id)) {
+                Some((zone_tt, _)) => {
+                    if self.fallbacks.insert((tt, id.clone())) {
+                        debug!(
+                            "Zone `{}` of the `{}` fare rules found in the `{}` zones",
+                            id, tt, zone_tt
+                        );
+                    }
+                    *zone_tt
+                }
+                None => {
+                    return Err(KdiError::DanglingReference {
+                        record: record(),
+                        field,
+                        value: id,
+                    })
+                }
+            }
+        };
+
+        Ok(format!("ZONE_{}", to_correct_id(&zone_tt, &id)))
+    }
+}
+
+/// `<fare>[_<origin>_<destination>][_route_<route>][_contains_<zone>]`, the parts present in the rule
+fn fare_rule_id(fare_rule: &KdiFareRule) -> String {
+    let mut id = fare_rule.fare.clone();
+    if fare_rule.origin.is_some() || fare_rule.destination.is_some() {
+        id.push('_');
+        id.push_str(fare_rule.origin.as_deref().unwrap_or_default());
+        id.push('_');
+        id.push_str(fare_rule.destination.as_deref().unwrap_or_default());
+    }
+    if let Some(route) = &fare_rule.route {
+        id.push_str("_route_");
+        id.push_str(route);
+    }
+    if let Some(contains) = &fare_rule.contains {
+        id.push_str("_contains_");
+        id.push_str(contains);
+    }
+    id
+}
+
+pub fn align_parking_stop_car_sharing(
+    source: &KmlSource,
+    parking_stops: &mut Vec<KdiParkingStop>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let parking_stop = || -> Result<KdiParkingStop, KdiError> {
+            Ok(KdiParkingStop {
+                id: kml_id(source, i),
+                location: kml_id(source, i),
+                ptype: KdiParkingStopEnum::CarSharing,
+                address: kml_data(source, i, placemark, "via")?.to_string(),
+                total_slots: kml_data_parse(source, i, placemark, "auto")?,
+            })
+        };
+        if let Some(parking_stop) = rejections.check("Core::ParkingStop", parking_stop())? {
+            parking_stops.push(parking_stop);
+        }
+    }
+
+    parking_stops.sort_by(|a, b| a.location.cmp(&b.location));
+
+    Ok(())
+}
+
+pub fn align_parking_stop_centro_in_bici(
+    source: &KmlSource,
+    parking_stops: &mut Vec<KdiParkingStop>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let parking_stop = || -> Result<KdiParkingStop, KdiError> {
+            Ok(KdiParkingStop {
+                id: kml_id(source, i),
+                location: kml_id(source, i),
+                ptype: KdiParkingStopEnum::BikeSharing,
+                address: kml_data(source, i, placemark, "desc")?.to_string(),
+                total_slots: kml_data_parse(source, i, placemark, "cicloposteggi")?,
+            })
+        };
+        if let Some(parking_stop) = rejections.check("Core::ParkingStop", parking_stop())? {
+            parking_stops.push(parking_stop);
+        }
+    }
+
+    parking_stops.sort_by(|a, b| a.location.cmp(&b.location));
+
+    Ok(())
+}
+
+pub fn align_parking_stop_parcheggio_protetto_biciclette(
+    source: &KmlSource,
+    parking_stops: &mut Vec<KdiParkingStop>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let parking_stop = || -> Result<KdiParkingStop, KdiError> {
+            Ok(KdiParkingStop {
+                id: kml_id(source, i),
+                location: kml_id(source, i),
+                ptype: KdiParkingStopEnum::BikeParking,
+                address: kml_data(source, i, placemark, "via")?.to_string(),
+                total_slots: kml_data_parse(source, i, placemark, "posti")?,
+            })
+        };
+        if let Some(parking_stop) = rejections.check("Core::ParkingStop", parking_stop())? {
+            parking_stops.push(parking_stop);
+        }
+    }
+
+    parking_stops.sort_by(|a, b| a.location.cmp(&b.location));
+
+    Ok(())
+}
+
+pub fn align_parking_stop_taxi(
+    source: &KmlSource,
+    parking_stops: &mut Vec<KdiParkingStop>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, placemark) in source.kml.document.folder.placemarks.iter().enumerate() {
+        let parking_stop = || -> Result<KdiParkingStop, KdiError> {
+            Ok(KdiParkingStop {
+                id: kml_id(source, i),
+                location: kml_id(source, i),
+                ptype: KdiParkingStopEnum::Taxi,
+                address: kml_data(source, i, placemark, "indirizzo")?.to_string(),
+                total_slots: 1,
+            })
+        };
+        if let Some(parking_stop) = rejections.check("Core::ParkingStop", parking_stop())? {
+            parking_stops.push(parking_stop);
+        }
+    }
+
+    parking_stops.sort_by(|a, b| a.location.cmp(&b.location));
+
+    Ok(())
+}
+
+pub fn align_fare(
+    source: &FareSource,
+    fares: &mut Vec<KdiFare>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    let tt = source.tt;
+    let names = if matches!(tt, TT::ExtraUrban) {
+        [
+            (
+                "tariffegtfsextraurbano/fare_attributes_extraurbano.txt",
+                KdiFareEnum::Cash,
+            ),
+            (
+                "tariffegtfsextraurbano/fare_attributes_extraurbano_cartascalare.txt",
+                KdiFareEnum::Cartascalare,
+            ),
+            (
+                "tariffegtfsextraurbano/fare_attributes_extraurbano_mobile.txt",
+                KdiFareEnum::Mobile,
+            ),
+        ]
+    } else {
+        [
+            (
+                "tariffegtfsurbano/fare_attributes_urbano.txt",
+                KdiFareEnum::Cash,
+            ),
+            (
+                "tariffegtfsurbano/fare_attributes_urbano_cartascalare.txt",
+                KdiFareEnum::Cartascalare,
+            ),
+            (
+                "tariffegtfsurbano/fare_attributes_urbano_mobile.txt",
+                KdiFareEnum::Mobile,
+            ),
+        ]
+    };
+
+    for (name, ftype) in names {
+        for fare in fare_entry::<KdiFare>(source, name, "Core::Fare", rejections)? {
+            fares.push(KdiFare {
+                id: to_correct_id(&tt, &fare.id),
+                ftype: ftype.clone(),
+                ..fare
+            });
+        }
+    }
+
+    fares.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_bike_sharing_stop(
+    source: &BikeSharingSource,
+    bike_sharing_stops: &mut Vec<KdiBikeSharingStop>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for (i, bs) in source.bike_sharing.iter().enumerate() {
+        let bike_sharing_stop = bike_sharing_coordinate(source, i).map(|_| KdiBikeSharingStop {
+            id: format!("{}_{}", source.prefix, bs.id),
+            location: format!("{}_{}", source.prefix, bs.id),
+            ptype: KdiParkingStopEnum::BikeSharing,
+            address: bs.address.clone(),
+            total_slots: bs.total_slots,
+            free_slots: bs.slots,
+            bikes: bs.bikes,
+        });
+        if let Some(bike_sharing_stop) =
+            rejections.check("Core::BikeSharingStop", bike_sharing_stop)?
+        {
+            bike_sharing_stops.push(bike_sharing_stop);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn align_public_transport_stop(
+    source: &GtfsSource,
+    public_transport_stops: &mut Vec<KdiPublicTransportStop>,
+) -> Result<(), KdiError> {
+    let tt = source.tt;
+    for stop in source.gtfs.stops.values() {
+        public_transport_stops.push(KdiPublicTransportStop {
+            id: to_correct_id(&tt, &stop.id),
+            location: to_correct_id(&tt, &stop.id),
+            zone: stop
+                .zone_id
+                .as_ref()
+                .map(|zone_id| format!("ZONE_{}", to_correct_id(&tt, zone_id))),
+            ptype: Vec::new(),
+            weelchair: KdiSupportedEnum::from(stop.wheelchair_boarding),
+            tt,
+        });
+    }
+
+    public_transport_stops.sort_by(|a, b| a.location.cmp(&b.location));
+
+    Ok(())
+}
+
+/// Transport modes of every public transport stop, derived from the routes of the trips serving it.
+/// Stops served by no trip have no transport mode.
+/// Stop times of unknown trips and trips of unknown routes are skipped, they are reported by the
+/// referential integrity check
+pub fn align_public_transport_stop_transport(
+    public_transport_stops: &mut [KdiPublicTransportStop],
+    stop_times: &[KdiStopTime],
+    trips: &[KdiTrip],
+    routes: &[KdiRoute],
+) {
+    let route_transports: HashMap<&str, &KdiTransportEnum> = routes
+        .iter()
+        .map(|route| (route.id.as_str(), &route.transport))
+        .collect();
+    let trip_transports: HashMap<&str, &KdiTransportEnum> = trips
+        .iter()
+        .filter_map(|trip| {
+            route_transports
+                .get(trip.route.as_str())
+                .map(|&transport| (trip.id.as_str(), transport))
+        })
+        .collect();
+    let mut stop_transports: HashMap<&str, BTreeSet<&KdiTransportEnum>> = HashMap::new();
+    for stop_time in stop_times {
+        if let Some(&transport) = trip_transports.get(stop_time.trip.as_str()) {
+            stop_transports
+                .entry(stop_time.stop.as_str())
+                .or_default()
+                .insert(transport);
+        }
+    }
+
+    for stop in public_transport_stops.iter_mut() {
+        stop.ptype = stop_transports
+            .get(stop.location.as_str())
+            .map(|transports| transports.iter().map(|&t| t.clone()).collect())
+            .unwrap_or_default();
+    }
+}
+
+pub fn align_stop_time(
+    source: &GtfsSource,
+    stop_times: &mut Vec<KdiStopTime>,
+) -> Result<(), KdiError> {
+    let tt = source.tt;
+    for trip in source.gtfs.trips.values() {
+        for stop_time in &trip.stop_times {
+            stop_times.push(KdiStopTime {
+                id: format!(
+                    "{}_{}",
+                    to_correct_id(&tt, &trip.id),
+                    to_correct_id(&tt, &stop_time.stop.id)
+                ),
+                trip: to_correct_id(&tt, &trip.id),
+                stop: to_correct_id(&tt, &stop_time.stop.id),
+                arrival: stop_time.arrival_time.map(stop_time_date_time),
+                departure: stop_time.departure_time.map(stop_time_date_time),
+                sequence: usize::from(stop_time.stop_sequence),
+            })
+        }
+    }
+
+    stop_times.sort_by(|a, b| {
+        a.trip
+            .cmp(&b.trip)
+            .then_with(|| a.sequence.cmp(&b.sequence))
+    });
+
+    Ok(())
+}
+
+/// Date time of a `GTFS` stop time, in seconds since the start of the service day.
+/// Days are counted from `0000-01-01`, e.g. `25:10:00` is `0000-01-02T01:10:00`
+fn stop_time_date_time(seconds: u32) -> String {
+    let start = NaiveDate::from_ymd_opt(0, 1, 1)
+        .unwrap_or(NaiveDate::MIN)
+        .and_time(NaiveTime::MIN);
+    (start + TimeDelta::seconds(i64::from(seconds)))
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string()
+}
+
+pub fn align_trip<'a>(
+    source: &'a GtfsSource,
+    trips: &mut Vec<KdiTrip<'a>>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    let tt = source.tt;
+    for trip in source.gtfs.trips.values() {
+        let kdi_trip = || -> Result<KdiTrip<'a>, KdiError> {
+            Ok(KdiTrip {
+                id: to_correct_id(&tt, &trip.id),
+                route: to_correct_id(&tt, &trip.route_id),
+                calendar: to_correct_id(&tt, &trip.service_id),
+                name: trip
+                    .trip_headsign
+                    .as_ref()
+                    .ok_or_else(|| KdiError::MissingField {
+                        record: gtfs_record(source, &trip.id),
+                        field: "trip_headsign",
+                    })?,
+                direction: KdiDirectionEnum::from(trip.direction_id.ok_or_else(|| {
+                    KdiError::MissingField {
+                        record: gtfs_record(source, &trip.id),
+                        field: "direction_id",
+                    }
+                })?),
+                weelchair: KdiSupportedEnum::from(trip.wheelchair_accessible),
+                bike: KdiSupportedEnum::from(trip.bikes_allowed),
+            })
+        };
+        if let Some(trip) = rejections.check("Core::Trip", kdi_trip())? {
+            trips.push(trip);
+        }
+    }
+
+    trips.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+pub fn align_route<'a>(
+    source: &'a GtfsSource,
+    routes: &mut Vec<KdiRoute<'a>>,
+    rejections: &mut Rejections,
+) -> Result<(), KdiError> {
+    for route in source.gtfs.routes.values() {
+        let kdi_route = || -> Result<KdiRoute<'a>, KdiError> {
+            Ok(KdiRoute {
+                id: to_correct_id(&source.tt, &route.id),
+                agency: route
+                    .agency_id
+                    .as_ref()
+                    .ok_or_else(|| KdiError::MissingField {
+                        record: gtfs_record(source, &route.id),
+                        field: "agency_id",
+                    })?,
+                short_name: &route.short_name,
+                long_name: &route.long_name,
+                transport: KdiTransportEnum::try_from(route.route_type).map_err(|route_type| {
+                    KdiError::UnknownRouteType {
+                        record: gtfs_record(source, &route.id),
+                        route_type,
+                    }
+                })?,
+            })
+        };
+        if let Some(route) = rejections.check("Core::Route", kdi_route())? {
+            routes.push(route);
+        }
+    }
+
+    routes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    /// Fare archive of the `tt` network with the `zones` entry and `rules` as its first fare rules entry
+    fn fare_source(dir: &Path, tt: TT, zones: &str, rules: &str) -> FareSource {
+        let path = dir.join(format!("{}.zip", tt));
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let (zones_name, rules_name) = (zones_entry(tt), fare_rules_entry(tt));
+        let header = rules.lines().next().unwrap();
+        for name in [zones_name, rules_name] {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            let content = if name == zones_name { zones } else { rules };
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        for suffix in ["cartascalare", "mobile"] {
+            let name = rules_name.replace(".txt", &format!("_{}.txt", suffix));
+            zip.start_file(name, FileOptions::default()).unwrap();
+            writeln!(zip, "{}", header).unwrap();
+        }
+        zip.finish().unwrap();
+
+        FareSource { path, tt }
+    }
+
+    fn fare_rules_entry(tt: TT) -> &'static str {
+        if matches!(tt, TT::ExtraUrban) {
+            "tariffegtfsextraurbano/fare_rules_extraurbano.txt"
+        } else {
+            "tariffegtfsurbano/fare_rules_urbano.txt"
+        }
+    }
+
+    fn fare_sources(dir: &Path) -> Vec<FareSource> {
+        vec![
+            fare_source(
+                dir,
+                TT::ExtraUrban,
+                "ZONE_ID, ZONE_NAME, ZONE_LAT, ZONE_LON\n\
+                 0001,Trento,46.07,11.12\n\
+                 0935,Lavis,46.14,11.11\n",
+                "FARE_ID, ROUTE_ID, ORIGIN_ID, DESTINATION_ID, CONTAINS_ID, INSTR_ID\n\
+                 EXT_4KM,,0001,0935,,\n\
+                 EXT_LINE,R1,0001,0001,,\n",
+            ),
+            fare_source(
+                dir,
+                TT::Urban,
+                "ZONE_ID, ZONE_NAME, ZONE_LAT, ZONE_LON\n\
+                 10002,Lavis,46.13,11.10\n\
+                 10101,Rovereto,north,east\n",
+                "FARE_ID, ROUTE_ID, ORIGIN_ID, DESTINATION_ID, CONTAINS_ID\n\
+                 URB_70,,,,10002\n\
+                 URB_70,,,,0935\n\
+                 URB_DAY,,,,\n\
+                 URB_70,,,,19030\n",
+            ),
+        ]
+    }
+
+    fn fare_rule(
+        fare: &str,
+        route: Option<&str>,
+        origin: Option<&str>,
+        destination: Option<&str>,
+        contains: Option<&str>,
+    ) -> KdiFareRule {
+        let mut fare_rule = KdiFareRule {
+            id: String::new(),
+            fare: fare.to_string(),
+            route: route.map(str::to_string),
+            origin: origin.map(str::to_string),
+            destination: destination.map(str::to_string),
+            contains: contains.map(str::to_string),
+        };
+        fare_rule.id = fare_rule_id(&fare_rule);
+        fare_rule
+    }
+
+    #[test]
+    fn fare_rule_ids() {
+        let id = |fare_rule: KdiFareRule| fare_rule.id;
+        assert_eq!(id(fare_rule("U_DAY", None, None, None, None)), "U_DAY");
+        assert_eq!(
+            id(fare_rule("EU_4KM", None, Some("A"), Some("B"), None)),
+            "EU_4KM_A_B"
+        );
+        assert_eq!(
+            id(fare_rule("EU_4KM", None, Some("A"), None, None)),
+            "EU_4KM_A_"
+        );
+        assert_eq!(
+            id(fare_rule(
+                "EU_LINE",
+                Some("EU_R1"),
+                Some("A"),
+                Some("B"),
+                None
+            )),
+            "EU_LINE_A_B_route_EU_R1"
+        );
+        assert_eq!(
+            id(fare_rule("U_70", Some("U_5"), None, None, Some("C"))),
+            "U_70_route_U_5_contains_C"
+        );
+    }
+
+    #[test]
+    fn fare_zone_lookup() {
+        let mut zones = FareZones::default();
+        zones
+            .zones
+            .push((TT::ExtraUrban, HashSet::from(["0001".to_string()])));
+        zones.zones.push((
+            TT::Urban,
+            HashSet::from(["0001".to_string(), "10002".to_string()]),
+        ));
+        let record = || Record::new(Path::new("fare_rules.txt"), Some(1), None);
+        let mut location = |tt, id: &str| zones.location(tt, id.to_string(), "contains", record);
+
+        // Own network first, even when the zone is also in another network
+        assert_eq!(location(TT::Urban, "0001").unwrap(), "ZONE_U_0001");
+        assert_eq!(location(TT::ExtraUrban, "0001").unwrap(), "ZONE_EU_0001");
+        // Then the other networks
+        assert_eq!(location(TT::ExtraUrban, "10002").unwrap(), "ZONE_U_10002");
+        assert!(matches!(
+            location(TT::Urban, "19030"),
+            Err(KdiError::DanglingReference { field: "contains", value, .. }) if value == "19030"
+        ));
+        assert_eq!(
+            zones.fallbacks,
+            HashSet::from([(TT::ExtraUrban, "10002".to_string())])
+        );
+    }
+
+    #[test]
+    fn fare_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = fare_sources(dir.path());
+        let mut rejections = Rejections::new(true);
+        let mut fare_rules = Vec::new();
+        align_fare_rule(&sources, &mut rejections, |entry, fare_rule| {
+            fare_rules.push((entry.to_path_buf(), fare_rule));
+            Ok(())
+        })
+        .unwrap();
+
+        let ids: Vec<&str> = fare_rules
+            .iter()
+            .map(|(_, rule)| rule.id.as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                "EU_EXT_4KM_ZONE_EU_0001_ZONE_EU_0935",
+                "EU_EXT_LINE_ZONE_EU_0001_ZONE_EU_0001_route_EU_R1",
+                "U_URB_70_contains_ZONE_U_10002",
+                "U_URB_70_contains_ZONE_EU_0935",
+                "U_URB_DAY",
+            ]
+        );
+        assert_eq!(
+            fare_rules[0].0,
+            sources[0].path.join(fare_rules_entry(TT::ExtraUrban))
+        );
+        assert_eq!(
+            fare_rules[4].0,
+            sources[1].path.join(fare_rules_entry(TT::Urban))
+        );
+
+        // Missing fields are no longer defaulted to the `0001` zone
+        let (_, day) = &fare_rules[4];
+        assert_eq!(
+            (&day.route, &day.origin, &day.destination, &day.contains),
+            (&None, &None, &None, &None)
+        );
+
+        // The invalid urban zone and the rule of the unknown `19030` zone
+        let rejected: Vec<(&str, Option<usize>)> = rejections
+            .rejections()
+            .iter()
+            .map(|rejection| (rejection.entity, rejection.record.index))
+            .collect();
+        assert_eq!(
+            rejected,
+            [("Common::Location", Some(2)), ("Core::FareRule", Some(4))]
+        );
+    }
+
+    #[test]
+    fn fare_rules_strict() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = fare_sources(dir.path());
+        let result = align_fare_rule(&sources[..1], &mut Rejections::new(false), |_, _| Ok(()));
+        assert!(result.is_ok());
+        let result = align_fare_rule(&sources, &mut Rejections::new(false), |_, _| Ok(()));
+        assert!(matches!(result, Err(KdiError::Csv { .. })));
+    }
+}
@@ -0,0 +1,1575 @@
+use log::{debug, info, warn};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use strum::VariantNames;
+
+use crate::align::{self, TT};
+use crate::config::KmlLayer;
+use crate::enums::{
+    KdiCurrencyEnum, KdiDirectionEnum, KdiExceptionEnum, KdiFareEnum, KdiParkingStopEnum,
+    KdiPaymentEnum, KdiSupportedEnum, KdiTransportEnum,
+};
+use crate::error::KdiError;
+use crate::geography::{GeographyCheck, LocationIssue};
+use crate::geojson::GeoJsonWriter;
+use crate::gtfs::{
+    GtfsAgency, GtfsCalendar, GtfsCalendarDate, GtfsFareAttribute, GtfsFareRule, GtfsRoute,
+    GtfsStop, GtfsStopTime, GtfsTrip, GtfsWriter,
+};
+use crate::integrity::{self, IntegrityCheck};
+use crate::kml::{self, KmlFormat, KmlWriter};
+use crate::neo4j::ImportFile;
+use crate::output::{Compression, OutputFile, OutputOptions};
+use crate::parquet::{self, ParquetWriter};
+use crate::rdf::{self, RdfFormat, RdfOptions};
+use crate::rejection::Rejections;
+use crate::service::{self, ServiceDates};
+use crate::source::{FareSource, Sources};
+use crate::sqlite::{self, SqliteWriter, Table};
+use crate::stop_times::StopTimeCheck;
+use crate::structs::{
+    KdiAgency, KdiBikeSharingStop, KdiCalendar, KdiCalendarException, KdiFare, KdiFareRule,
+    KdiLocation, KdiParkingStop, KdiPublicTransportStop, KdiRoute, KdiStopTime, KdiTrip,
+};
+
+/// Entity groups of the alignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    /// `Location`, `CalendarException`, `Calendar` and `Agency`
+    Common,
+    /// `FareRule`, `ParkingStop`, `Fare`, `BikeSharingStop`, `StopTime`, `Trip`, `Route` and `PublicTransportStop`
+    Core,
+    /// Every `*Enum`
+    Contextual,
+}
+
+impl Group {
+    /// Whether the group is aligned from the data sources
+    pub fn requires_sources(&self) -> bool {
+        matches!(*self, Group::Common | Group::Core)
+    }
+}
+
+/// Aligned entities, borrowing from the [`Sources`] they were aligned from.
+/// Entities of a group that has not been aligned are left empty
+#[derive(Debug, Default)]
+pub struct Alignment<'a> {
+    pub groups: Vec<Group>,
+    // Common
+    pub locations: Vec<KdiLocation>,
+    pub calendar_exceptions: Vec<KdiCalendarException>,
+    pub calendars: Vec<KdiCalendar>,
+    pub agencies: Vec<KdiAgency<'a>>,
+    /// Locations corrected or flagged by the geographic check
+    pub location_issues: Vec<LocationIssue>,
+    // Core
+    /// Fare rules are streamed from their sources, see [`Alignment::fare_rules`]
+    pub fare_rule_count: usize,
+    fare_sources: &'a [FareSource],
+    pub parking_stops: Vec<KdiParkingStop>,
+    pub fares: Vec<KdiFare>,
+    pub bike_sharing_stops: Vec<KdiBikeSharingStop>,
+    pub stop_times: Vec<KdiStopTime>,
+    pub trips: Vec<KdiTrip<'a>>,
+    pub routes: Vec<KdiRoute<'a>>,
+    pub public_transport_stops: Vec<KdiPublicTransportStop>,
+    // Records dropped by a lenient alignment
+    pub rejections: Rejections,
+}
+
+impl<'a> Alignment<'a> {
+    /// Align the entities of `groups` from `sources`.
+    /// A `lenient` alignment rejects invalid records instead of failing
+    pub fn align(
+        sources: &'a Sources,
+        groups: &[Group],
+        lenient: bool,
+    ) -> Result<Alignment<'a>, KdiError> {
+        let mut alignment = Alignment {
+            groups: groups.to_vec(),
+            rejections: Rejections::new(lenient),
+            ..Default::default()
+        };
+        let common = alignment.contains(Group::Common);
+        let core = alignment.contains(Group::Core);
+
+        // Every step only reads `sources`, so they all run concurrently.
+        // Results and rejections are then merged in the order of a sequential run
+        let mut locations = Step::new("Common::Location", lenient);
+        let mut calendar_exceptions = Step::new("Common::CalendarException", lenient);
+        let mut calendars = Step::new("Common::Calendar", lenient);
+        let mut agencies = Step::new("Common::Agency", lenient);
+        let mut fare_rules = Step::new("Core::FareRule", lenient);
+        let mut parking_stops = Step::new("Core::ParkingStop", lenient);
+        let mut fares = Step::new("Core::Fare", lenient);
+        let mut bike_sharing_stops = Step::new("Core::BikeSharingStop", lenient);
+        let mut stop_times = Step::new("Core::StopTime", lenient);
+        let mut trips = Step::new("Core::Trip", lenient);
+        let mut routes = Step::new("Core::Route", lenient);
+        let mut public_transport_stops = Step::new("Core::PublicTransportStop", lenient);
+        rayon::scope(|s| {
+            // --- COMMON
+            if common {
+                s.spawn(|_| locations.run(|r| align_locations(sources, r)));
+                s.spawn(|_| calendar_exceptions.run(|_| align_calendar_exceptions(sources)));
+                s.spawn(|_| calendars.run(|_| align_calendars(sources)));
+                s.spawn(|_| agencies.run(|r| align_agencies(sources, r)));
+            }
+            // --- CORE
+            if core {
+                s.spawn(|_| fare_rules.run(|r| align_fare_rules(sources, r)));
+                s.spawn(|_| parking_stops.run(|r| align_parking_stops(sources, r)));
+                s.spawn(|_| fares.run(|r| align_fares(sources, r)));
+                s.spawn(|_| bike_sharing_stops.run(|r| align_bike_sharing_stops(sources, r)));
+                s.spawn(|_| stop_times.run(|_| align_stop_times(sources)));
+                s.spawn(|_| trips.run(|r| align_trips(sources, r)));
+                s.spawn(|_| routes.run(|r| align_routes(sources, r)));
+                s.spawn(|_| public_transport_stops.run(|_| align_public_transport_stops(sources)));
+            }
+        });
+
+        let rejections = &mut alignment.rejections;
+        if common {
+            (alignment.locations, alignment.location_issues) = locations.finish(rejections)?;
+            alignment.calendar_exceptions = calendar_exceptions.finish(rejections)?;
+            alignment.calendars = calendars.finish(rejections)?;
+            alignment.agencies = agencies.finish(rejections)?;
+        }
+        if core {
+            alignment.fare_rule_count = fare_rules.finish(rejections)?;
+            alignment.fare_sources = &sources.fares;
+            alignment.parking_stops = parking_stops.finish(rejections)?;
+            alignment.fares = fares.finish(rejections)?;
+            alignment.bike_sharing_stops = bike_sharing_stops.finish(rejections)?;
+            alignment.stop_times = stop_times.finish(rejections)?;
+            alignment.trips = trips.finish(rejections)?;
+            alignment.routes = routes.finish(rejections)?;
+            alignment.public_transport_stops = public_transport_stops.finish(rejections)?;
+            debug!("Aligning `Core::PublicTransportStop` transport modes");
+            align::align_public_transport_stop_transport(
+                &mut alignment.public_transport_stops,
+                &alignment.stop_times,
+                &alignment.trips,
+                &alignment.routes,
+            );
+        }
+        if lenient {
+            alignment.rejections.log_summary();
+        }
+
+        Ok(alignment)
+    }
+
+    /// Stream the aligned fare rules to `f`, in source and entry order
+    pub fn fare_rules(
+        &self,
+        mut f: impl FnMut(KdiFareRule) -> Result<(), KdiError>,
+    ) -> Result<(), KdiError> {
+        self.fare_rule_entries(|_, fare_rule| f(fare_rule))
+    }
+
+    /// Stream the aligned fare rules to `f` with the path of their entry
+    fn fare_rule_entries(
+        &self,
+        f: impl FnMut(&Path, KdiFareRule) -> Result<(), KdiError>,
+    ) -> Result<(), KdiError> {
+        // Invalid rows have already been rejected, and reported, by the alignment
+        let mut rejections = Rejections::new(self.rejections.is_lenient());
+        align::align_fare_rule(self.fare_sources, &mut rejections, f)
+    }
+
+    /// Check the referential integrity of the aligned entities: dangling references
+    /// and duplicate keys are errors, entities that are never referenced are warnings
+    pub fn check_integrity(&self) -> Result<(), KdiError> {
+        let mut check = IntegrityCheck::new();
+        if self.contains(Group::Common) {
+            check.records(&integrity::LOCATIONS, &self.locations)?;
+            check.records(&integrity::CALENDARS, &self.calendars)?;
+            check.records(&integrity::AGENCIES, &self.agencies)?;
+        }
+        if self.contains(Group::Core) {
+            check.records(&integrity::FARES, &self.fares)?;
+            check.records(&integrity::ROUTES, &self.routes)?;
+            check.records(&integrity::TRIPS, &self.trips)?;
+            check.records(
+                &integrity::PUBLIC_TRANSPORT_STOPS,
+                &self.public_transport_stops,
+            )?;
+            check.records(&integrity::PARKING_STOPS, &self.parking_stops)?;
+            check.records(&integrity::BIKE_SHARING_STOPS, &self.bike_sharing_stops)?;
+        }
+        if self.contains(Group::Common) {
+            check.records(&integrity::CALENDAR_EXCEPTIONS, &self.calendar_exceptions)?;
+        }
+        if self.contains(Group::Core) {
+            check.records(&integrity::STOP_TIMES, &self.stop_times)?;
+            check.file(
+                &integrity::FARE_RULES,
+                Path::new(integrity::FARE_RULES.name),
+            );
+            self.fare_rules(|fare_rule| {
+                let record = serde_json::to_value(&fare_rule).map_err(|source| KdiError::Json {
+                    file: Path::new(integrity::FARE_RULES.name).to_path_buf(),
+                    source,
+                })?;
+                check.record(&record);
+                Ok(())
+            })?;
+        }
+
+        check.finish()
+    }
+
+    /// Check the times, sequences and speeds of the stop times of every trip.
+    /// Speeds are checked only when the locations of the stops have been aligned
+    pub fn check_stop_times(&self) -> Result<(), KdiError> {
+        if !self.contains(Group::Core) {
+            return Ok(());
+        }
+
+        StopTimeCheck::new(&self.public_transport_stops, &self.locations).check(&self.stop_times)
+    }
+
+    /// Dates every aligned calendar, with its exceptions, is active on
+    pub fn service_dates(&self) -> Result<ServiceDates, KdiError> {
+        service::expand(&self.calendars, &self.calendar_exceptions)
+    }
+
+    /// Check that the calendar exceptions fall inside the range of their calendar
+    /// and that every service, and so every trip, is active on at least one date
+    pub fn check_calendars(&self) -> Result<(), KdiError> {
+        if !self.contains(Group::Common) {
+            return Ok(());
+        }
+
+        service::check(&self.calendars, &self.calendar_exceptions, &self.trips)
+    }
+
+    /// Write the dates every service is active on as the `service_dates` file under `output`
+    pub fn write_service_dates(
+        &self,
+        output: &Path,
+        options: &OutputOptions,
+    ) -> Result<(), KdiError> {
+        let services = self.service_dates()?;
+        options.write_records(output, "service_dates", service::records(&services))
+    }
+
+    /// Whether the entities of `group` have been aligned
+    pub fn contains(&self, group: Group) -> bool {
+        self.groups.contains(&group)
+    }
+
+    /// Write the aligned entities under `output`
+    pub fn write(&self, output: &Path, options: &OutputOptions) -> Result<(), KdiError> {
+        if self.contains(Group::Common) {
+            self.write_common(output, options)?;
+        }
+        if self.contains(Group::Core) {
+            self.write_core(output, options)?;
+        }
+        if self.contains(Group::Contextual) {
+            write_contextual(output, options)?;
+        }
+        self.write_reports(output, options)
+    }
+
+    /// Write the `location_issues` of the geographic check and, when lenient, the `rejected`
+    /// records under `output`. Reports are never split, whatever the chunk size
+    fn write_reports(&self, output: &Path, options: &OutputOptions) -> Result<(), KdiError> {
+        let options = OutputOptions {
+            chunk_size: None,
+            ..*options
+        };
+        if self.contains(Group::Common) {
+            options.write_records(output, "location_issues", &self.location_issues)?;
+        }
+        if self.rejections.is_lenient() {
+            options.write_records(output, "rejected", self.rejections.rejections())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the aligned entities as `JSON` files under `output`
+    pub fn write_json(&self, output: &Path) -> Result<(), KdiError> {
+        self.write(output, &OutputOptions::default())
+    }
+
+    /// Write the aligned entities as `RDF` files under `output`
+    pub fn write_rdf(&self, output: &Path, options: &RdfOptions) -> Result<(), KdiError> {
+        if options.format == RdfFormat::JsonLd {
+            write_json_ld_context(output, options)?;
+        }
+        if self.contains(Group::Common) {
+            self.write_rdf_common(output, options)?;
+        }
+        if self.contains(Group::Core) {
+            self.write_rdf_core(output, options)?;
+        }
+        if self.contains(Group::Contextual) {
+            write_rdf_contextual(output, options)?;
+        }
+        let json = OutputOptions {
+            compression: options.compression,
+            ..OutputOptions::default()
+        };
+        self.write_reports(output, &json)
+    }
+
+    /// Write the locations and the stops joined with their location as `GeoJSON` layers under `output`:
+    /// `locations`, one `parking_stops_*` layer per [`KdiParkingStopEnum`] and
+    /// one `public_transport_stops_*` layer per [`TT`]
+    pub fn write_geojson(&self, output: &Path, compression: Compression) -> Result<(), KdiError> {
+        if !self.contains(Group::Common) {
+            warn!("Locations not aligned, skipping `GeoJSON` layers");
+            return Ok(());
+        }
+        let locations: HashMap<&str, &KdiLocation> = self
+            .locations
+            .iter()
+            .map(|location| (location.id.as_str(), location))
+            .collect();
+        let mut writer = GeoJsonWriter::create(output, "locations", compression)?;
+        for location in &self.locations {
+            writer.write(&location.id, location, location)?;
+        }
+        writer.finish()?;
+        if !self.contains(Group::Core) {
+            return Ok(());
+        }
+
+        for variant in KdiParkingStopEnum::VARIANTS {
+            let name = format!("parking_stops_{}", snake_case(variant));
+            let mut writer = GeoJsonWriter::create(output, &name, compression)?;
+            for stop in &self.parking_stops {
+                if format!("{:?}", stop.ptype) == *variant {
+                    write_feature(&mut writer, &locations, &stop.id, &stop.location, stop)?;
+                }
+            }
+            for stop in &self.bike_sharing_stops {
+                if format!("{:?}", stop.ptype) == *variant {
+                    write_feature(&mut writer, &locations, &stop.id, &stop.location, stop)?;
+                }
+            }
+            writer.finish()?;
+        }
+        for (tt, name) in [(TT::Urban, "urban"), (TT::ExtraUrban, "extraurban")] {
+            let name = format!("public_transport_stops_{}", name);
+            let mut writer = GeoJsonWriter::create(output, &name, compression)?;
+            for stop in self
+                .public_transport_stops
+                .iter()
+                .filter(|stop| stop.tt == tt)
+            {
+                write_feature(&mut writer, &locations, &stop.id, &stop.location, stop)?;
+            }
+            writer.finish()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the stops as a single styled `KML` document (`mobility.kml` or `mobility.kmz`) under `output`:
+    /// a folder per [`KdiParkingStopEnum`] with the parking and bike sharing stops and
+    /// a folder per transport mode with the public transport stops serving it
+    pub fn write_kml(
+        &self,
+        output: &Path,
+        format: KmlFormat,
+        compression: Compression,
+    ) -> Result<(), KdiError> {
+        if !self.contains(Group::Common) || !self.contains(Group::Core) {
+            warn!("Common and Core entities not both aligned, skipping `KML` document");
+            return Ok(());
+        }
+        let locations: HashMap<&str, &KdiLocation> = self
+            .locations
+            .iter()
+            .map(|location| (location.id.as_str(), location))
+            .collect();
+        let parking: Vec<(&str, String)> = KdiParkingStopEnum::VARIANTS
+            .iter()
+            .filter(|variant| {
+                self.parking_stops
+                    .iter()
+                    .map(|stop| &stop.ptype)
+                    .chain(self.bike_sharing_stops.iter().map(|stop| &stop.ptype))
+                    .any(|ptype| format!("{:?}", ptype) == **variant)
+            })
+            .map(|variant| (*variant, format!("parking_stops_{}", snake_case(variant))))
+            .collect();
+        let mut modes: BTreeMap<&KdiTransportEnum, Vec<&KdiPublicTransportStop>> = BTreeMap::new();
+        for stop in &self.public_transport_stops {
+            for mode in &stop.ptype {
+                modes.entry(mode).or_default().push(stop);
+            }
+        }
+        let mode_style = |mode: &KdiTransportEnum| {
+            let mut name = snake_case(&format!("{:?}", mode));
+            name.retain(|c| c.is_ascii_alphanumeric() || c == '_');
+            format!("public_transport_stops_{}", name)
+        };
+
+        let mut writer = KmlWriter::create(output, "mobility", format, compression)?;
+        let styles = parking
+            .iter()
+            .map(|(_, style)| style.clone())
+            .chain(modes.keys().map(|mode| mode_style(mode)));
+        for (style, color) in styles.zip(kml::COLORS.iter().cycle()) {
+            writer.style(&style, color)?;
+        }
+
+        writer.open_folder("Parking stops")?;
+        for (variant, style) in &parking {
+            writer.open_folder(variant)?;
+            for stop in &self.parking_stops {
+                if format!("{:?}", stop.ptype) == *variant {
+                    write_placemark(
+                        &mut writer,
+                        &locations,
+                        style,
+                        &stop.id,
+                        &stop.location,
+                        stop,
+                    )?;
+                }
+            }
+            for stop in &self.bike_sharing_stops {
+                if format!("{:?}", stop.ptype) == *variant {
+                    write_placemark(
+                        &mut writer,
+                        &locations,
+                        style,
+                        &stop.id,
+                        &stop.location,
+                        stop,
+                    )?;
+                }
+            }
+            writer.close_folder()?;
+        }
+        writer.close_folder()?;
+
+        writer.open_folder("Public transport stops")?;
+        for (mode, stops) in &modes {
+            let style = mode_style(mode);
+            writer.open_folder(&format!("{:?}", mode))?;
+            for stop in stops {
+                write_placemark(
+                    &mut writer,
+                    &locations,
+                    &style,
+                    &stop.id,
+                    &stop.location,
+                    stop,
+                )?;
+            }
+            writer.close_folder()?;
+        }
+        writer.close_folder()?;
+
+        writer.finish()
+    }
+
+    /// Write the public transport entities back as a single merged `GTFS` feed (`gtfs.zip`) under `output`
+    pub fn write_gtfs(&self, output: &Path) -> Result<(), KdiError> {
+        if !self.contains(Group::Common) || !self.contains(Group::Core) {
+            warn!("Common and Core entities not both aligned, skipping `GTFS` feed");
+            return Ok(());
+        }
+        let locations: HashMap<&str, &KdiLocation> = self
+            .locations
+            .iter()
+            .map(|location| (location.id.as_str(), location))
+            .collect();
+        let mut feed = GtfsWriter::create(output, "gtfs")?;
+
+        feed.write_file(
+            "agency.txt",
+            self.agencies.iter().map(|a| Ok(GtfsAgency::from(a))),
+        )?;
+        let mut fare_rules = feed.file("fare_rules.txt")?;
+        self.fare_rules(|fare_rule| fare_rules.write(&GtfsFareRule::from(fare_rule)))?;
+        fare_rules.finish()?;
+        feed.write_file(
+            "fare_attributes.txt",
+            self.fares
+                .iter()
+                .map(|fare| Ok(GtfsFareAttribute::from(fare))),
+        )?;
+
+        let stops = self.public_transport_stops.iter().filter_map(|stop| {
+            match locations.get(stop.location.as_str()) {
+                Some(location) => Some(GtfsStop::stop(stop, location)),
+                None => {
+                    warn!(
+                        "Stop `{}` has unknown location `{}`, skipping",
+                        stop.id, stop.location
+                    );
+                    None
+                }
+            }
+        });
+        feed.write_file("stops.txt", stops.map(Ok))?;
+        feed.write_file(
+            "routes.txt",
+            self.routes.iter().map(|r| Ok(GtfsRoute::from(r))),
+        )?;
+        feed.write_file(
+            "trips.txt",
+            self.trips.iter().map(|t| Ok(GtfsTrip::from(t))),
+        )?;
+        feed.write_file(
+            "stop_times.txt",
+            self.stop_times.iter().map(GtfsStopTime::try_from),
+        )?;
+        feed.write_file(
+            "calendar.txt",
+            self.calendars.iter().map(GtfsCalendar::try_from),
+        )?;
+        feed.write_file(
+            "calendar_dates.txt",
+            self.calendar_exceptions
+                .iter()
+                .map(GtfsCalendarDate::try_from),
+        )?;
+
+        feed.finish()
+    }
+
+    /// Write the aligned entities as a single `SQLite` database (`alignment.sqlite`) under `output`,
+    /// one table per entity with foreign keys between them.
+    /// Rows with a dangling reference are rejected when lenient, abort the write otherwise
+    pub fn write_sqlite(&self, output: &Path) -> Result<(), KdiError> {
+        let mut tables: Vec<&Table> = Vec::new();
+        if self.contains(Group::Common) {
+            tables.extend(sqlite::COMMON);
+        }
+        if self.contains(Group::Core) {
+            tables.extend(sqlite::CORE);
+        }
+        let db = SqliteWriter::create(output, "alignment", &tables)?;
+
+        // --- COMMON
+        if self.contains(Group::Common) {
+            db.write(&self.locations)?;
+            db.write(&self.calendar_exceptions)?;
+            db.write(&self.calendars)?;
+            db.write(&self.agencies)?;
+        }
+
+        // --- CORE
+        if self.contains(Group::Core) {
+            self.fare_rules(|fare_rule| db.write([&fare_rule]))?;
+            db.write(&self.parking_stops)?;
+            db.write(&self.fares)?;
+            db.write(&self.bike_sharing_stops)?;
+            db.write(&self.stop_times)?;
+            db.write(&self.trips)?;
+            db.write(&self.routes)?;
+            db.write(&self.public_transport_stops)?;
+            for stop in &self.public_transport_stops {
+                for ptype in &stop.ptype {
+                    db.insert(
+                        &sqlite::PUBLIC_TRANSPORT_STOP_TYPES,
+                        vec![stop.id.clone().into(), format!("{:?}", ptype).into()],
+                    )?;
+                }
+            }
+        }
+
+        // Rows rejected by the foreign key check are reported apart from the alignment rejections
+        let mut rejections = Rejections::new(self.rejections.is_lenient());
+        db.write_rejections(self.rejections.rejections())?;
+        db.finish(&mut rejections)?;
+        if !rejections.rejections().is_empty() {
+            info!("Rejected rows with dangling references from the `SQLite` database");
+            rejections.log_summary();
+        }
+
+        Ok(())
+    }
+
+    /// Write one `Parquet` file per entity table under `output`, with typed columns:
+    /// enums are dictionary encoded, dates are `Date32` and stop times are durations
+    /// since the start of the service day
+    pub fn write_parquet(&self, output: &Path, compression: Compression) -> Result<(), KdiError> {
+        // --- COMMON
+        if self.contains(Group::Common) {
+            parquet::write(output, compression, &self.locations)?;
+            parquet::write(output, compression, &self.calendar_exceptions)?;
+            parquet::write(output, compression, &self.calendars)?;
+            parquet::write(output, compression, &self.agencies)?;
+        }
+
+        // --- CORE
+        if self.contains(Group::Core) {
+            let mut writer = ParquetWriter::create(output, compression)?;
+            self.fare_rules(|fare_rule| writer.write(&fare_rule))?;
+            writer.finish()?;
+            parquet::write(output, compression, &self.parking_stops)?;
+            parquet::write(output, compression, &self.fares)?;
+            parquet::write(output, compression, &self.bike_sharing_stops)?;
+            parquet::write(output, compression, &self.stop_times)?;
+            parquet::write(output, compression, &self.trips)?;
+            parquet::write(output, compression, &self.routes)?;
+            parquet::write(output, compression, &self.public_transport_stops)?;
+        }
+
+        let json = OutputOptions {
+            compression,
+            ..OutputOptions::default()
+        };
+        self.write_reports(output, &json)
+    }
+
+    /// Write the aligned entities as `neo4j-admin import` node and relationship `CSV` files under `output`.
+    /// Locations, stops, trips, routes, calendars, fares, agencies and zones are nodes,
+    /// the references between them are relationships
+    pub fn write_neo4j(&self, output: &Path, compression: Compression) -> Result<(), KdiError> {
+        if !self.contains(Group::Common) || !self.contains(Group::Core) {
+            warn!("Common and Core entities not both aligned, skipping `Neo4j` import files");
+            return Ok(());
+        }
+        let compression = ImportFile::compression(compression);
+        let nodes = |name, label, properties: &[&str]| {
+            ImportFile::nodes(output, name, compression, label, properties)
+        };
+        let relationships = |name, kind, ids, properties: &[&str]| {
+            ImportFile::relationships(output, name, compression, kind, ids, properties)
+        };
+
+        // --- NODES
+        let mut file = nodes(
+            "locations",
+            "Location",
+            &["name", "latitude:double", "longitude:double"],
+        )?;
+        for location in &self.locations {
+            file.write(&[
+                &location.id,
+                &location.name,
+                &location.latitude.to_string(),
+                &location.longitude.to_string(),
+            ])?;
+        }
+        file.finish()?;
+        let mut file = nodes(
+            "calendars",
+            "Calendar",
+            &[
+                "startDate:localdatetime",
+                "endDate:localdatetime",
+                "monday:boolean",
+                "tuesday:boolean",
+                "wednesday:boolean",
+                "thursday:boolean",
+                "friday:boolean",
+                "saturday:boolean",
+                "sunday:boolean",
+            ],
+        )?;
+        for calendar in &self.calendars {
+            file.write(&[
+                &calendar.id,
+                &calendar.start_date,
+                &calendar.end_date,
+                &calendar.monday.to_string(),
+                &calendar.tuesday.to_string(),
+                &calendar.wednesday.to_string(),
+                &calendar.thursday.to_string(),
+                &calendar.friday.to_string(),
+                &calendar.saturday.to_string(),
+                &calendar.sunday.to_string(),
+            ])?;
+        }
+        file.finish()?;
+        let mut file = nodes("agencies", "Agency", &["name", "email", "phone", "url"])?;
+        for agency in &self.agencies {
+            file.write(&[
+                agency.id,
+                agency.name,
+                agency.email,
+                agency.phone,
+                agency.url,
+            ])?;
+        }
+        file.finish()?;
+        let mut file = nodes(
+            "fares",
+            "Fare",
+            &[
+                "price:double",
+                "currency",
+                "type",
+                "payment",
+                "duration:long",
+            ],
+        )?;
+        for fare in &self.fares {
+            file.write(&[
+                &fare.id,
+                &fare.price.to_string(),
+                &format!("{:?}", fare.currency),
+                &format!("{:?}", fare.ftype),
+                &format!("{:?}", fare.payment),
+                &fare.duration.to_string(),
+            ])?;
+        }
+        file.finish()?;
+        let mut file = nodes(
+            "parking_stops",
+            "ParkingStop",
+            &["type", "address", "totalSlots:long"],
+        )?;
+        for stop in &self.parking_stops {
+            file.write(&[
+                &stop.id,
+                &format!("{:?}", stop.ptype),
+                &stop.address,
+                &stop.total_slots.to_string(),
+            ])?;
+        }
+        file.finish()?;
+        let mut file = nodes(
+            "bike_sharing_stops",
+            "BikeSharingStop",
+            &[
+                "type",
+                "address",
+                "totalSlots:long",
+                "freeSlots:long",
+                "bikes:long",
+            ],
+        )?;
+        for stop in &self.bike_sharing_stops {
+            file.write(&[
+                &stop.id,
+                &format!("{:?}", stop.ptype),
+                &stop.address,
+                &stop.total_slots.to_string(),
+                &stop.free_slots.to_string(),
+                &stop.bikes.to_string(),
+            ])?;
+        }
+        file.finish()?;
+        let mut file = nodes(
+            "public_transport_stops",
+            "PublicTransportStop",
+            &["type:string[]", "weelchair"],
+        )?;
+        for stop in &self.public_transport_stops {
+            // Array values are separated by `;`
+            let ptype: Vec<String> = stop.ptype.iter().map(|t| format!("{:?}", t)).collect();
+            file.write(&[&stop.id, &ptype.join(";"), &format!("{:?}", stop.weelchair)])?;
+        }
+        file.finish()?;
+        let mut file = nodes("routes", "Route", &["shortName", "longName", "transport"])?;
+        for route in &self.routes {
+            file.write(&[
+                &route.id,
+                route.short_name,
+                route.long_name,
+                &format!("{:?}", route.transport),
+            ])?;
+        }
+        file.finish()?;
+        let mut file = nodes("trips", "Trip", &["name", "direction", "weelchair", "bike"])?;
+        for trip in &self.trips {
+            file.write(&[
+                &trip.id,
+                trip.name,
+                &format!("{:?}", trip.direction),
+                &format!("{:?}", trip.weelchair),
+                &format!("{:?}", trip.bike),
+            ])?;
+        }
+        file.finish()?;
+
+        // --- RELATIONSHIPS
+        for (name, start, stops) in [
+            (
+                "parking_stops_located_at",
+                "ParkingStop",
+                self.parking_stops
+                    .iter()
+                    .map(|stop| (&stop.id, &stop.location))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "bike_sharing_stops_located_at",
+                "BikeSharingStop",
+                self.bike_sharing_stops
+                    .iter()
+                    .map(|stop| (&stop.id, &stop.location))
+                    .collect(),
+            ),
+            (
+                "public_transport_stops_located_at",
+                "PublicTransportStop",
+                self.public_transport_stops
+                    .iter()
+                    .map(|stop| (&stop.id, &stop.location))
+                    .collect(),
+            ),
+        ] {
+            let mut file = relationships(name, "LOCATED_AT", (start, "Location"), &[])?;
+            for (stop, location) in stops {
+                file.write(&[stop, location])?;
+            }
+            file.finish()?;
+        }
+        let mut file = relationships(
+            "belongs_to_zone",
+            "BELONGS_TO_ZONE",
+            ("PublicTransportStop", "Zone"),
+            &[],
+        )?;
+        for stop in &self.public_transport_stops {
+            if let Some(zone) = &stop.zone {
+                file.write(&[&stop.id, zone])?;
+            }
+        }
+        file.finish()?;
+        let mut file = relationships(
+            "serves",
+            "SERVES",
+            ("Trip", "PublicTransportStop"),
+            &[
+                "arrival:localdatetime",
+                "departure:localdatetime",
+                "sequence:long",
+            ],
+        )?;
+        for stop_time in &self.stop_times {
+            file.write(&[
+                &stop_time.trip,
+                &stop_time.stop,
+                stop_time.arrival.as_deref().unwrap_or_default(),
+                stop_time.departure.as_deref().unwrap_or_default(),
+                &stop_time.sequence.to_string(),
+            ])?;
+        }
+        file.finish()?;
+        let mut file = relationships("operates_on", "OPERATES_ON", ("Trip", "Calendar"), &[])?;
+        for trip in &self.trips {
+            file.write(&[&trip.id, &trip.calendar])?;
+        }
+        file.finish()?;
+        let mut file = relationships("part_of", "PART_OF", ("Trip", "Route"), &[])?;
+        for trip in &self.trips {
+            file.write(&[&trip.id, &trip.route])?;
+        }
+        file.finish()?;
+        let mut file = relationships("operated_by", "OPERATED_BY", ("Route", "Agency"), &[])?;
+        for route in &self.routes {
+            file.write(&[&route.id, route.agency])?;
+        }
+        file.finish()?;
+
+        // Fare rules are streamed, the zones they use are collected on the way.
+        // The same rule may be listed more than once in an entry of the fare sources,
+        // duplicates are only looked for among the rules of the current entry
+        let mut zones: BTreeSet<String> = self
+            .public_transport_stops
+            .iter()
+            .filter_map(|stop| stop.zone.clone())
+            .collect();
+        let mut entry = PathBuf::new();
+        let mut fare_rules = HashSet::new();
+        let mut between = relationships(
+            "applies_between",
+            "APPLIES_BETWEEN",
+            ("Zone", "Zone"),
+            &["fare", "route"],
+        )?;
+        let mut within = relationships(
+            "applies_within",
+            "APPLIES_WITHIN",
+            ("Fare", "Zone"),
+            &["route"],
+        )?;
+        let mut skipped = 0;
+        self.fare_rule_entries(|fare_rule_entry, fare_rule| {
+            if fare_rule_entry != entry {
+                entry = fare_rule_entry.to_path_buf();
+                fare_rules.clear();
+            }
+            if !fare_rules.insert(fare_rule.id.clone()) {
+                return Ok(());
+            }
+            let route = fare_rule.route.as_deref().unwrap_or_default();
+            match (
+                &fare_rule.origin,
+                &fare_rule.destination,
+                &fare_rule.contains,
+            ) {
+                (Some(origin), Some(destination), _) => {
+                    between.write(&[origin, destination, &fare_rule.fare, route])?
+                }
+                (None, None, Some(contains)) => {
+                    within.write(&[&fare_rule.fare, contains, route])?
+                }
+                _ => skipped += 1,
+            }
+            zones.extend(fare_rule.zones().cloned());
+            Ok(())
+        })?;
+        between.finish()?;
+        within.finish()?;
+        if skipped > 0 {
+            warn!(
+                "Skipping {} fare rules with neither both an origin and a destination nor a contained zone",
+                skipped
+            );
+        }
+        let mut file = nodes("zones", "Zone", &[])?;
+        for zone in &zones {
+            file.write(&[zone])?;
+        }
+        file.finish()?;
+        let mut file = relationships("zones_located_at", "LOCATED_AT", ("Zone", "Location"), &[])?;
+        for zone in self
+            .locations
+            .iter()
+            .filter(|location| zones.contains(&location.id))
+        {
+            file.write(&[&zone.id, &zone.id])?;
+        }
+        file.finish()
+    }
+
+    fn write_common(&self, output: &Path, options: &OutputOptions) -> Result<(), KdiError> {
+        options.write_records(output, "locations", &self.locations)?;
+        options.write_records(output, "calendar_exceptions", &self.calendar_exceptions)?;
+        options.write_records(output, "calendars", &self.calendars)?;
+        options.write_records(output, "agencies", &self.agencies)?;
+
+        Ok(())
+    }
+
+    fn write_core(&self, output: &Path, options: &OutputOptions) -> Result<(), KdiError> {
+        let mut fare_rules = options.records_writer(output, "fare_rules")?;
+        self.fare_rules(|fare_rule| fare_rules.write(&fare_rule))?;
+        fare_rules.finish()?;
+        options.write_records(output, "parking_stops", &self.parking_stops)?;
+        options.write_records(output, "fares", &self.fares)?;
+        options.write_records(output, "bike_sharing_stops", &self.bike_sharing_stops)?;
+        options.write_records(output, "stop_times", &self.stop_times)?;
+        options.write_records(output, "trips", &self.trips)?;
+        options.write_records(output, "routes", &self.routes)?;
+        options.write_records(
+            output,
+            "public_transport_stops",
+            &self.public_transport_stops,
+        )?;
+
+        Ok(())
+    }
+
+    fn write_rdf_common(&self, output: &Path, options: &RdfOptions) -> Result<(), KdiError> {
+        options.write_entities(output, "locations", &self.locations)?;
+        options.write_entities(output, "calendar_exceptions", &self.calendar_exceptions)?;
+        options.write_entities(output, "calendars", &self.calendars)?;
+        options.write_entities(output, "agencies", &self.agencies)?;
+
+        Ok(())
+    }
+
+    fn write_rdf_core(&self, output: &Path, options: &RdfOptions) -> Result<(), KdiError> {
+        // Zones are only referenced by ID, collect them to declare their class
+        let mut zones: BTreeSet<String> = self
+            .public_transport_stops
+            .iter()
+            .filter_map(|stop| stop.zone.clone())
+            .collect();
+        let mut fare_rules = options.writer(output, "fare_rules")?;
+        self.fare_rules(|fare_rule| {
+            fare_rules.write(&fare_rule)?;
+            zones.extend(fare_rule.zones().cloned());
+            Ok(())
+        })?;
+        fare_rules.finish()?;
+        let mut writer = options.writer(output, "zones")?;
+        for zone in &zones {
+            writer.write_resource(rdf::ZONE_CLASS, zone)?;
+        }
+        writer.finish()?;
+        options.write_entities(output, "parking_stops", &self.parking_stops)?;
+        options.write_entities(output, "fares", &self.fares)?;
+        options.write_entities(output, "bike_sharing_stops", &self.bike_sharing_stops)?;
+        options.write_entities(output, "stop_times", &self.stop_times)?;
+        options.write_entities(output, "trips", &self.trips)?;
+        options.write_entities(output, "routes", &self.routes)?;
+        options.write_entities(
+            output,
+            "public_transport_stops",
+            &self.public_transport_stops,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Alignment step run on its own thread, with its own rejections
+struct Step<T> {
+    name: &'static str,
+    rejections: Rejections,
+    result: Option<Result<T, KdiError>>,
+}
+
+impl<T> Step<T> {
+    fn new(name: &'static str, lenient: bool) -> Step<T> {
+        Step {
+            name,
+            rejections: Rejections::new(lenient),
+            result: None,
+        }
+    }
+
+    fn run(&mut self, f: impl FnOnce(&mut Rejections) -> Result<T, KdiError>) {
+        self.result = Some(f(&mut self.rejections));
+    }
+
+    /// Merge the step rejections into `rejections` and return its result
+    fn finish(self, rejections: &mut Rejections) -> Result<T, KdiError> {
+        rejections.extend(self.rejections);
+        self.result
+            .unwrap_or(Err(KdiError::StepNotRun { step: self.name }))
+    }
+}
+
+fn align_locations(
+    sources: &Sources,
+    rejections: &mut Rejections,
+) -> Result<(Vec<KdiLocation>, Vec<LocationIssue>), KdiError> {
+    // - Location
+    let mut locations: Vec<KdiLocation> = Vec::new();
+    // Every source is checked against the area once aligned
+    let mut check = GeographyCheck::new(&sources.area);
+    info!("Aligning `Common::Location`");
+    // Zone
+    for source in &sources.fares {
+        debug!(
+            "Aligning `Common::Location::Zone` from `{}`",
+            source.path.display()
+        );
+        check.align(&mut locations, &source.path, |aligned| {
+            align::align_location_zone(source, aligned, rejections)
+        })?;
+    }
+    // PublicTransportStop
+    for source in &sources.gtfs {
+        debug!(
+            "Aligning `Common::Location::PublicTransportStop` from `{}`",
+            source.path.display()
+        );
+        check.align(&mut locations, &source.path, |aligned| {
+            align::align_location_public_transport_stop(source, aligned, rejections)
+        })?;
+    }
+    // CarSharing, CentroInBici, ParcheggioProtettoBiciclette and Taxi
+    for source in &sources.kml {
+        debug!(
+            "Aligning `Common::Location::{:?}` from `{}`",
+            source.layer,
+            source.path.display()
+        );
+        check.align(&mut locations, &source.path, |aligned| match source.layer {
+            KmlLayer::CarSharing => align::align_location_car_sharing(source, aligned, rejections),
+            KmlLayer::CentroInBici => {
+                align::align_location_centro_in_bici(source, aligned, rejections)
+            }
+            KmlLayer::ParcheggioProtettoBiciclette => {
+                align::align_location_parcheggio_protetto_biciclette(source, aligned, rejections)
+            }
+            KmlLayer::Taxi => align::align_location_taxi(source, aligned, rejections),
+        })?;
+    }
+    // Zones and stops are sorted by ID, bike sharing stations follow in source order
+    locations.sort_by(|a, b| a.id.cmp(&b.id));
+    // BikeSharing
+    for source in &sources.bike_sharing {
+        debug!(
+            "Aligning `Common::Location::Bikesharing` from `{}`",
+            source.path.display()
+        );
+        check.align(&mut locations, &source.path, |aligned| {
+            align::align_location_bike_sharing(source, aligned, rejections)
+        })?;
+    }
+    let issues = check.finish();
+
+    Ok((locations, issues))
+}
+
+fn align_calendar_exceptions(sources: &Sources) -> Result<Vec<KdiCalendarException>, KdiError> {
+    // - CalendarException
+    let mut calendar_exceptions: Vec<KdiCalendarException> = Vec::new();
+    info!("Aligning `Common::CalendarException`");
+    for source in &sources.gtfs {
+        debug!(
+            "Aligning `Common::CalendarException` from `{}`",
+            source.path.display()
+        );
+        align::align_calendar_exception(source, &mut calendar_exceptions)?;
+    }
+
+    Ok(calendar_exceptions)
+}
+
+fn align_calendars(sources: &Sources) -> Result<Vec<KdiCalendar>, KdiError> {
+    // - Calendar
+    let mut calendars: Vec<KdiCalendar> = Vec::new();
+    info!("Aligning `Common::Calendar`");
+    for source in &sources.gtfs {
+        debug!(
+            "Aligning `Common::Calendar` from `{}`",
+            source.path.display()
+        );
+        align::align_calendar(source, &mut calendars)?;
+    }
+
+    Ok(calendars)
+}
+
+fn align_agencies<'a>(
+    sources: &'a Sources,
+    rejections: &mut Rejections,
+) -> Result<Vec<KdiAgency<'a>>, KdiError> {
+    // - Agency
+    let mut agencies: Vec<KdiAgency<'a>> = Vec::new();
+    info!("Aligning `Common::Agency`");
+    for source in &sources.gtfs {
+        debug!("Aligning `Common::Agency` from `{}`", source.path.display());
+        align::align_agency(source, &mut agencies, rejections)?;
+    }
+
+    Ok(agencies)
+}
+
+fn align_fare_rules(sources: &Sources, rejections: &mut Rejections) -> Result<usize, KdiError> {
+    // - FareRule
+    // Too many to be kept in memory, rows are only validated here and streamed again on every write
+    info!("Aligning `Core:FareRule`");
+    let mut fare_rule_count: usize = 0;
+    align::align_fare_rule(&sources.fares, rejections, |_, _| {
+        fare_rule_count += 1;
+        Ok(())
+    })?;
+
+    Ok(fare_rule_count)
+}
+
+fn align_parking_stops(
+    sources: &Sources,
+    rejections: &mut Rejections,
+) -> Result<Vec<KdiParkingStop>, KdiError> {
+    // - ParkingStop
+    let mut parking_stops: Vec<KdiParkingStop> = Vec::new();
+    info!("Aligning `Core::ParkingStop`");
+    // CarSharing, CentroInBici, ParcheggioProtettoBiciclette and Taxi
+    for source in &sources.kml {
+        debug!(
+            "Aligning `Core::ParkingStop::{:?}` from `{}`",
+            source.layer,
+            source.path.display()
+        );
+        match source.layer {
+            KmlLayer::CarSharing => {
+                align::align_parking_stop_car_sharing(source, &mut parking_stops, rejections)?
+            }
+            KmlLayer::CentroInBici => {
+                align::align_parking_stop_centro_in_bici(source, &mut parking_stops, rejections)?
+            }
+            KmlLayer::ParcheggioProtettoBiciclette => {
+                align::align_parking_stop_parcheggio_protetto_biciclette(
+                    source,
+                    &mut parking_stops,
+                    rejections,
+                )?
+            }
+            KmlLayer::Taxi => {
+                align::align_parking_stop_taxi(source, &mut parking_stops, rejections)?
+            }
+        }
+    }
+
+    Ok(parking_stops)
+}
+
+fn align_fares(sources: &Sources, rejections: &mut Rejections) -> Result<Vec<KdiFare>, KdiError> {
+    // - Fare
+    let mut fares: Vec<KdiFare> = Vec::new();
+    info!("Aligning `Core::Fare`");
+    for source in &sources.fares {
+        debug!("Aligning `Core::Fare` from `{}`", source.path.display());
+        align::align_fare(source, &mut fares, rejections)?;
+    }
+
+    Ok(fares)
+}
+
+fn align_bike_sharing_stops(
+    sources: &Sources,
+    rejections: &mut Rejections,
+) -> Result<Vec<KdiBikeSharingStop>, KdiError> {
+    // - BikeSharingStop
+    let mut bike_sharing_stops: Vec<KdiBikeSharingStop> = Vec::new();
+    info!("Aligning `Core::BikeSharingStop`");
+    for source in &sources.bike_sharing {
+        debug!(
+            "Aligning `Core::BikeSharingStop` from `{}`",
+            source.path.display()
+        );
+        align::align_bike_sharing_stop(source, &mut bike_sharing_stops, rejections)?;
+    }
+
+    Ok(bike_sharing_stops)
+}
+
+fn align_stop_times(sources: &Sources) -> Result<Vec<KdiStopTime>, KdiError> {
+    // - StopTime
+    let mut stop_times: Vec<KdiStopTime> = Vec::new();
+    info!("Aligning `Core::StopTime`");
+    for source in &sources.gtfs {
+        debug!("Aligning `Core::StopTime` from `{}`", source.path.display());
+        align::align_stop_time(source, &mut stop_times)?;
+    }
+
+    Ok(stop_times)
+}
+
+fn align_trips<'a>(
+    sources: &'a Sources,
+    rejections: &mut Rejections,
+) -> Result<Vec<KdiTrip<'a>>, KdiError> {
+    // - Trip
+    let mut trips: Vec<KdiTrip<'a>> = Vec::new();
+    info!("Aligning `Common::Trip`");
+    for source in &sources.gtfs {
+        debug!("Aligning `Common::Trip` from `{}`", source.path.display());
+        align::align_trip(source, &mut trips, rejections)?;
+    }
+
+    Ok(trips)
+}
+
+fn align_routes<'a>(
+    sources: &'a Sources,
+    rejections: &mut Rejections,
+) -> Result<Vec<KdiRoute<'a>>, KdiError> {
+    // - Route
+    let mut routes: Vec<KdiRoute<'a>> = Vec::new();
+    info!("Aligning `Common::Route`");
+    for source in &sources.gtfs {
+        debug!("Aligning `Common::Route` from `{}`", source.path.display());
+        align::align_route(source, &mut routes, rejections)?;
+    }
+
+    Ok(routes)
+}
+
+fn align_public_transport_stops(
+    sources: &Sources,
+) -> Result<Vec<KdiPublicTransportStop>, KdiError> {
+    // - PublicTransportStop
+    let mut public_transport_stops: Vec<KdiPublicTransportStop> = Vec::new();
+    info!("Aligning `Core::PublicTransportStop`");
+    for source in &sources.gtfs {
+        debug!(
+            "Aligning `Core::PublicTransportStop` from `{}`",
+            source.path.display()
+        );
+        align::align_public_transport_stop(source, &mut public_transport_stops)?;
+    }
+
+    Ok(public_transport_stops)
+}
+
+fn write_rdf_contextual(output: &Path, options: &RdfOptions) -> Result<(), KdiError> {
+    info!("Aligning `Contextual::*`");
+    for enumeration in rdf::ENUMERATIONS {
+        let mut writer = options.writer(output, &snake_case(enumeration.class))?;
+        for variant in enumeration.variants {
+            writer.write_resource(enumeration.class, variant)?;
+        }
+        writer.finish()?;
+    }
+
+    Ok(())
+}
+
+/// `snake_case` file name of a class, e.g. `parking_stop_enum` for `ParkingStopEnum`
+pub(crate) fn snake_case(class: &str) -> String {
+    let mut name = String::with_capacity(class.len() + 4);
+    for (i, c) in class.char_indices() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// Write the `id` stop as a feature at its `location`, skipping stops without a location
+fn write_feature<T: Serialize>(
+    writer: &mut GeoJsonWriter,
+    locations: &HashMap<&str, &KdiLocation>,
+    id: &str,
+    location: &str,
+    stop: &T,
+) -> Result<(), KdiError> {
+    match locations.get(location) {
+        Some(location) => writer.write(id, stop, location),
+        None => {
+            warn!("Skipping `{}` feature, unknown location `{}`", id, location);
+            Ok(())
+        }
+    }
+}
+
+/// Write the `id` stop as a `style` placemark at its `location`, skipping stops without a location
+fn write_placemark<T: Serialize>(
+    writer: &mut KmlWriter,
+    locations: &HashMap<&str, &KdiLocation>,
+    style: &str,
+    id: &str,
+    location: &str,
+    stop: &T,
+) -> Result<(), KdiError> {
+    match locations.get(location) {
+        Some(location) => writer.write(stop, style, location),
+        None => {
+            warn!(
+                "Skipping `{}` placemark, unknown location `{}`",
+                id, location
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Write the context referenced, uncompressed, by every `JSON-LD` file
+fn write_json_ld_context(output: &Path, options: &RdfOptions) -> Result<(), KdiError> {
+    let path = output.join(format!("{}.jsonld", rdf::JSON_LD_CONTEXT));
+    info!("Writing `{}` file", path.display());
+    let mut file = OutputFile::create(&path, Compression::None)?;
+    serde_json::to_writer_pretty(&mut file, &options.json_ld_context()).map_err(|source| {
+        KdiError::Json {
+            file: path.clone(),
+            source,
+        }
+    })?;
+    file.finish()
+}
+
+fn write_contextual(output: &Path, options: &OutputOptions) -> Result<(), KdiError> {
+    // --- CONTEXTUAL
+    info!("Aligning `Contextual::*`");
+    // - PaymentEnum
+    options.write_document(
+        output,
+        "payment_enum",
+        &json!({ "value": KdiPaymentEnum::VARIANTS }),
+    )?;
+    // - ParkingStopEnum
+    options.write_document(
+        output,
+        "parking_stop_enum",
+        &json!({ "value": KdiParkingStopEnum::VARIANTS }),
+    )?;
+    // - CurrencyEnum
+    options.write_document(
+        output,
+        "currency_enum",
+        &json!({ "value": KdiCurrencyEnum::VARIANTS }),
+    )?;
+    // - FareEnum
+    options.write_document(
+        output,
+        "fare_enum",
+        &json!({ "value": KdiFareEnum::VARIANTS }),
+    )?;
+    // - SupportedEnum
+    options.write_document(
+        output,
+        "supported_enum",
+        &json!({ "value": KdiSupportedEnum::VARIANTS }),
+    )?;
+    // - DirectionEnum
+    options.write_document(
+        output,
+        "direction_enum",
+        &json!({ "value": KdiDirectionEnum::VARIANTS }),
+    )?;
+    // - ExceptionEnum
+    options.write_document(
+        output,
+        "exception_enum",
+        &json!({ "value": KdiExceptionEnum::VARIANTS }),
+    )?;
+    // - TransportEnum
+    options.write_document(
+        output,
+        "transport_enum",
+        &json!({ "value": KdiTransportEnum::VARIANTS }),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Source};
+    use crate::geography::Area;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Urban network, fares, car sharing, taxi and bike sharing sources of the repository
+    fn config() -> Config {
+        let data = |file: &str| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("data")
+                .join(file)
+        };
+        Config {
+            sources: vec![
+                Source::Gtfs {
+                    path: data("urban.zip"),
+                    tt: TT::Urban,
+                },
+                Source::Fare {
+                    path: data("urban_fare.zip"),
+                    tt: TT::Urban,
+                },
+                Source::Kml {
+                    path: data("car_sharing.kml"),
+                    layer: KmlLayer::CarSharing,
+                    prefix: None,
+                },
+                Source::Kml {
+                    path: data("taxi.kml"),
+                    layer: KmlLayer::Taxi,
+                    prefix: None,
+                },
+                Source::BikeSharing {
+                    path: data("bikesharing_rovereto.json"),
+                    prefix: None,
+                },
+            ],
+            area: Area::default(),
+        }
+    }
+
+    /// Files written by the lenient alignment of `sources` run on `threads` threads, by name
+    fn align(sources: &Sources, threads: usize) -> BTreeMap<PathBuf, Vec<u8>> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let alignment = pool
+            .install(|| Alignment::align(sources, &[Group::Common, Group::Core], true))
+            .unwrap();
+        let output = tempfile::tempdir().unwrap();
+        alignment.write_json(output.path()).unwrap();
+
+        fs::read_dir(output.path())
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let content = fs::read(&path).unwrap();
+                (PathBuf::from(path.file_name().unwrap()), content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn alignment_does_not_depend_on_the_threads() {
+        let sources = Sources::read(&config()).unwrap();
+        let sequential = align(&sources, 1);
+        assert!(sequential.contains_key(Path::new("stop_times.json")));
+        assert!(sequential.contains_key(Path::new("rejected.json")));
+        assert_eq!(sequential, align(&sources, 8));
+    }
+
+    #[test]
+    fn geojson_layers() {
+        let sources = Sources::read(&config()).unwrap();
+        let alignment = Alignment::align(&sources, &[Group::Common, Group::Core], true).unwrap();
+        let output = tempfile::tempdir().unwrap();
+        alignment
+            .write_geojson(output.path(), Compression::None)
+            .unwrap();
+        let features = |name: &str| {
+            let path = output.path().join(format!("{}.geojson", name));
+            let collection: serde_json::Value =
+                serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+            collection["features"].as_array().unwrap().clone()
+        };
+
+        for variant in KdiParkingStopEnum::VARIANTS {
+            let features = features(&format!("parking_stops_{}", snake_case(variant)));
+            let stops = alignment
+                .parking_stops
+                .iter()
+                .map(|stop| &stop.ptype)
+                .chain(alignment.bike_sharing_stops.iter().map(|stop| &stop.ptype))
+                .filter(|ptype| format!("{:?}", ptype) == *variant)
+                .count();
+            assert_eq!(features.len(), stops, "{}", variant);
+            assert!(features
+                .iter()
+                .all(|feature| feature["properties"]["type"] == *variant));
+        }
+        assert!(!features("parking_stops_car_sharing").is_empty());
+        assert_eq!(
+            features("public_transport_stops_urban").len(),
+            alignment.public_transport_stops.len()
+        );
+        assert!(features("public_transport_stops_extraurban").is_empty());
+    }
+
+    #[test]
+    fn kml_folders() {
+        let sources = Sources::read(&config()).unwrap();
+        let alignment = Alignment::align(&sources, &[Group::Common, Group::Core], true).unwrap();
+        let output = tempfile::tempdir().unwrap();
+        alignment
+            .write_kml(output.path(), KmlFormat::Kml, Compression::None)
+            .unwrap();
+        let content = fs::read_to_string(output.path().join("mobility.kml")).unwrap();
+
+        assert!(content.contains(
+            "<Folder><name>Parking stops</name><Folder><name>BikeSharing</name><Placemark>"
+        ));
+        assert!(content.contains("<Folder><name>CarSharing</name><Placemark>"));
+        assert!(content.contains("<Folder><name>Taxi</name><Placemark>"));
+        assert!(!content.contains("<name>BikeParking</name>"));
+        assert!(content.contains(r#"<Style id="public_transport_stops_bus">"#));
+        assert!(content.contains(
+            "<Folder><name>Public transport stops</name><Folder><name>Bus</name><Placemark>"
+        ));
+        let placemarks = alignment.parking_stops.len()
+            + alignment.bike_sharing_stops.len()
+            + alignment
+                .public_transport_stops
+                .iter()
+                .map(|stop| stop.ptype.len())
+                .sum::<usize>();
+        assert_eq!(content.matches("<Placemark>").count(), placemarks);
+    }
+
+    #[test]
+    fn step_not_run() {
+        let step: Step<()> = Step::new("Core::Trip", false);
+        assert!(matches!(
+            step.finish(&mut Rejections::new(false)),
+            Err(KdiError::StepNotRun { step: "Core::Trip" })
+        ));
+    }
+}
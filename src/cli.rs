@@ -0,0 +1,262 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use kdi::config::{Config, KmlLayer, Source};
+use kdi::geography::Area;
+use kdi::rdf::RdfFormat;
+use kdi::{OutputOptions, RdfOptions, TT};
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "kdi",
+    version,
+    about = "KDI alignment | Trentino Transportation"
+)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
+pub enum Command {
+    /// Align the datasets and write the result as JSON, JSON Lines, RDF, JSON-LD, GeoJSON, GTFS, SQLite, Neo4j, Parquet or KML files
+    Align(AlignArgs),
+    /// Write the OWL ontology and the SHACL shapes of the entity schema as Turtle files
+    Ontology(OntologyArgs),
+    /// Write the JSON Schema of every JSON output file
+    Schema(SchemaArgs),
+    /// Validate the JSON and JSON Lines files of an alignment directory against their JSON Schema
+    /// and check the references between their records
+    Validate(ValidateArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AlignArgs {
+    /// Pipeline configuration file (`.toml`, `.yaml` or `.yml`) describing every data source.
+    /// When present the input file options below are ignored
+    #[clap(short, long)]
+    pub config: Option<PathBuf>,
+    /// Output directory, created when missing. A non-empty directory is refused unless `--force`
+    #[clap(short, long, default_value = "./alignment")]
+    pub output: PathBuf,
+    /// Remove the output directory and everything in it before writing the alignment
+    #[clap(long)]
+    pub force: bool,
+    /// Entity groups to align
+    #[clap(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "common,core,contextual"
+    )]
+    pub groups: Vec<Group>,
+    /// Output file format
+    #[clap(long, value_enum, default_value = "json")]
+    pub format: Format,
+    /// Output file compression
+    #[clap(long, value_enum, default_value = "none")]
+    pub compression: Compression,
+    /// Base IRI of the RDF resources, classes and properties
+    #[clap(long, default_value = kdi::rdf::DEFAULT_BASE_IRI)]
+    pub base_iri: String,
+    /// Also split every JSON entity file into `<entity>_<n>` files of at most `CHUNK_SIZE` records,
+    /// except `rejected`
+    #[clap(long)]
+    pub chunk_size: Option<NonZeroUsize>,
+    /// Skip invalid records and report them in the `rejected` file instead of aborting:
+    /// `rejected.jsonl` with `--format jsonl`, `rejected.json` otherwise, a table of SQLite databases
+    #[clap(long)]
+    pub lenient: bool,
+    /// Check the referential integrity, the stop times and the calendars of the alignment before
+    /// writing it, aborting on dangling references, duplicate IDs, inconsistent stop times,
+    /// exceptions outside of their calendar and never active services
+    #[clap(long)]
+    pub check: bool,
+    /// Also write the dates every service is active on in the `service_dates` file,
+    /// as `JSON Lines` with `--format jsonl` and `JSON` otherwise
+    #[clap(long)]
+    pub service_dates: bool,
+    /// Bounding box the aligned locations are checked against, in place of the default area
+    /// or of the `area` of the configuration file
+    #[clap(long, value_parser, value_name = "MINLON,MINLAT,MAXLON,MAXLAT")]
+    pub bbox: Option<Area>,
+    /// Extraurban GTFS zip
+    #[clap(long, default_value = "./data/extraurban.zip")]
+    pub extraurban: PathBuf,
+    /// Urban GTFS zip
+    #[clap(long, default_value = "./data/urban.zip")]
+    pub urban: PathBuf,
+    /// Extraurban fare zip
+    #[clap(long, default_value = "./data/extraurban_fare.zip")]
+    pub extraurban_fare: PathBuf,
+    /// Urban fare zip
+    #[clap(long, default_value = "./data/urban_fare.zip")]
+    pub urban_fare: PathBuf,
+    /// Car sharing KML
+    #[clap(long, default_value = "./data/car_sharing.kml")]
+    pub car_sharing: PathBuf,
+    /// Centro in bici KML
+    #[clap(long, default_value = "./data/centro_in_bici.kml")]
+    pub centro_in_bici: PathBuf,
+    /// Parcheggio protetto biciclette KML
+    #[clap(long, default_value = "./data/parcheggio_protetto_biciclette.kml")]
+    pub parcheggio_protetto_biciclette: PathBuf,
+    /// Taxi KML
+    #[clap(long, default_value = "./data/taxi.kml")]
+    pub taxi: PathBuf,
+    /// Bike sharing JSON files
+    #[clap(
+        long,
+        value_delimiter = ',',
+        default_value = "./data/bikesharing_lavis.json,./data/bikesharing_mezzocorona.json,./data/bikesharing_mezzolombardo.json,./data/bikesharing_rovereto.json,./data/bikesharing_sanmichelealladige.json,./data/bikesharing_trento.json"
+    )]
+    pub bike_sharing: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct OntologyArgs {
+    /// Output directory
+    #[clap(short, long, default_value = "./ontology")]
+    pub output: PathBuf,
+    /// Base IRI of the RDF resources, classes and properties
+    #[clap(long, default_value = kdi::rdf::DEFAULT_BASE_IRI)]
+    pub base_iri: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Output directory
+    #[clap(short, long, default_value = "./schema")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ValidateArgs {
+    /// Alignment directory
+    #[clap(default_value = "./alignment")]
+    pub input: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Group {
+    Common,
+    Core,
+    Contextual,
+}
+
+impl From<Group> for kdi::Group {
+    fn from(group: Group) -> Self {
+        match group {
+            Group::Common => kdi::Group::Common,
+            Group::Core => kdi::Group::Core,
+            Group::Contextual => kdi::Group::Contextual,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Json,
+    Jsonl,
+    Turtle,
+    NTriples,
+    JsonLd,
+    #[clap(name = "geojson")]
+    GeoJson,
+    Gtfs,
+    Sqlite,
+    Neo4j,
+    Parquet,
+    Kml,
+    Kmz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl From<Compression> for kdi::output::Compression {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => kdi::output::Compression::None,
+            Compression::Gzip => kdi::output::Compression::Gzip,
+            Compression::Zstd => kdi::output::Compression::Zstd,
+        }
+    }
+}
+
+impl AlignArgs {
+    pub fn to_output_options(&self, format: kdi::output::Format) -> OutputOptions {
+        OutputOptions {
+            format,
+            compression: self.compression.into(),
+            chunk_size: self.chunk_size,
+        }
+    }
+
+    pub fn to_rdf_options(&self, format: RdfFormat) -> RdfOptions {
+        RdfOptions {
+            format,
+            compression: self.compression.into(),
+            base_iri: self.base_iri.clone(),
+        }
+    }
+
+    pub fn to_config(&self) -> Config {
+        let mut sources = vec![
+            Source::Gtfs {
+                path: self.extraurban.clone(),
+                tt: TT::ExtraUrban,
+            },
+            Source::Gtfs {
+                path: self.urban.clone(),
+                tt: TT::Urban,
+            },
+            Source::Fare {
+                path: self.extraurban_fare.clone(),
+                tt: TT::ExtraUrban,
+            },
+            Source::Fare {
+                path: self.urban_fare.clone(),
+                tt: TT::Urban,
+            },
+            Source::Kml {
+                path: self.car_sharing.clone(),
+                layer: KmlLayer::CarSharing,
+                prefix: None,
+            },
+            Source::Kml {
+                path: self.centro_in_bici.clone(),
+                layer: KmlLayer::CentroInBici,
+                prefix: None,
+            },
+            Source::Kml {
+                path: self.parcheggio_protetto_biciclette.clone(),
+                layer: KmlLayer::ParcheggioProtettoBiciclette,
+                prefix: None,
+            },
+            Source::Kml {
+                path: self.taxi.clone(),
+                layer: KmlLayer::Taxi,
+                prefix: None,
+            },
+        ];
+        for path in &self.bike_sharing {
+            sources.push(Source::BikeSharing {
+                path: path.clone(),
+                prefix: None,
+            });
+        }
+
+        Config {
+            sources,
+            area: self.bbox.clone().unwrap_or_default(),
+        }
+    }
+}
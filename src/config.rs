@@ -0,0 +1,85 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::align::TT;
+use crate::error::KdiError;
+use crate::geography::Area;
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    #[serde(rename = "source")]
+    pub sources: Vec<Source>,
+    /// Area the aligned locations are checked against
+    #[serde(default)]
+    pub area: Area,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Source {
+    Gtfs {
+        path: PathBuf,
+        tt: TT,
+    },
+    Fare {
+        path: PathBuf,
+        tt: TT,
+    },
+    Kml {
+        path: PathBuf,
+        layer: KmlLayer,
+        prefix: Option<String>,
+    },
+    BikeSharing {
+        path: PathBuf,
+        prefix: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KmlLayer {
+    CarSharing,
+    CentroInBici,
+    ParcheggioProtettoBiciclette,
+    Taxi,
+}
+
+impl KmlLayer {
+    pub fn default_prefix(&self) -> &'static str {
+        match *self {
+            KmlLayer::CarSharing => "CS",
+            KmlLayer::CentroInBici => "CIB",
+            KmlLayer::ParcheggioProtettoBiciclette => "PPB",
+            KmlLayer::Taxi => "TX",
+        }
+    }
+}
+
+pub const BIKE_SHARING_DEFAULT_PREFIX: &str = "BS";
+
+impl Config {
+    pub fn from_file(path: &Path) -> Result<Config, KdiError> {
+        let content = fs::read_to_string(path).map_err(|source| KdiError::Io {
+            file: path.to_path_buf(),
+            source,
+        })?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&content).map_err(|source| KdiError::Yaml {
+                    file: path.to_path_buf(),
+                    source,
+                })
+            }
+            Some("toml") => toml::from_str(&content).map_err(|source| KdiError::Toml {
+                file: path.to_path_buf(),
+                source,
+            }),
+            _ => Err(KdiError::UnknownConfigFormat {
+                file: path.to_path_buf(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use gtfs_structures::{Availability, BikesAllowedType, DirectionType, Exception, RouteType};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_repr::Deserialize_repr;
+use strum_macros::{EnumString, EnumVariantNames};
+
+#[derive(Debug, Serialize, EnumString, EnumVariantNames)]
+#[serde(rename(serialize = "ParkingStopEnum"))]
+pub enum KdiParkingStopEnum {
+    BikeSharing,
+    BikeParking,
+    CarSharing,
+    Taxi,
+}
+
+#[derive(Debug, Serialize, Deserialize_repr, EnumString, EnumVariantNames)]
+#[repr(u8)]
+#[serde(rename(serialize = "PaymentEnum"))]
+pub enum KdiPaymentEnum {
+    OnBoard = 0,
+    BeforeBoarding = 1,
+}
+
+#[derive(Debug, Serialize, Deserialize, EnumString, EnumVariantNames)]
+#[serde(rename(serialize = "CurrencyEnum"))]
+#[allow(clippy::upper_case_acronyms)]
+pub enum KdiCurrencyEnum {
+    EUR,
+}
+
+#[derive(Debug, Default, Clone, Serialize, EnumString, EnumVariantNames)]
+#[serde(rename(serialize = "FareEnum"))]
+pub enum KdiFareEnum {
+    #[default]
+    Cash,
+    Cartascalare,
+    Mobile,
+}
+
+#[derive(Debug, Serialize, EnumString, EnumVariantNames)]
+#[serde(rename(serialize = "SupportedEnum"))]
+pub enum KdiSupportedEnum {
+    Unknown,
+    Supported,
+    NotSupported,
+}
+
+impl From<Availability> for KdiSupportedEnum {
+    fn from(availability: Availability) -> Self {
+        match availability {
+            Availability::Available => KdiSupportedEnum::Supported,
+            Availability::NotAvailable => KdiSupportedEnum::NotSupported,
+            _ => KdiSupportedEnum::Unknown,
+        }
+    }
+}
+
+impl From<BikesAllowedType> for KdiSupportedEnum {
+    fn from(bikes_allowed_type: BikesAllowedType) -> Self {
+        match bikes_allowed_type {
+            BikesAllowedType::AtLeastOneBike => KdiSupportedEnum::Supported,
+            BikesAllowedType::NoBikesAllowed => KdiSupportedEnum::NotSupported,
+            _ => KdiSupportedEnum::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, EnumString, EnumVariantNames)]
+#[serde(rename(serialize = "DirectionEnum"))]
+pub enum KdiDirectionEnum {
+    Outbound,
+    Inbound,
+}
+
+impl From<DirectionType> for KdiDirectionEnum {
+    fn from(direction_type: DirectionType) -> Self {
+        match direction_type {
+            DirectionType::Outbound => KdiDirectionEnum::Outbound,
+            DirectionType::Inbound => KdiDirectionEnum::Inbound,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, EnumString, EnumVariantNames)]
+#[serde(rename(serialize = "ExceptionEnum"))]
+pub enum KdiExceptionEnum {
+    Added,
+    Removed,
+}
+
+impl From<Exception> for KdiExceptionEnum {
+    fn from(exception: Exception) -> Self {
+        match exception {
+            Exception::Added => KdiExceptionEnum::Added,
+            Exception::Deleted => KdiExceptionEnum::Removed,
+        }
+    }
+}
+
+/// Transport mode of a route.
+/// Mapping of the basic and extended `GTFS` route types
+/// (<https://developers.google.com/transit/gtfs/reference/extended-route-types>)
+#[derive(Debug, EnumString, EnumVariantNames, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum KdiTransportEnum {
+    /// `2`, `100`-`199` and `300`-`399`
+    Train,
+    /// `3` and `700`-`899`
+    Bus,
+    /// `5`
+    CableCar,
+    /// `0` and `900`-`999`
+    Tram,
+    /// `1`, `400`-`499` and `500`-`699`
+    Subway,
+    /// `4`, `1000`-`1099` and `1200`-`1299`
+    Ferry,
+    /// `6` and `1300`-`1399`
+    Gondola,
+    /// `7` and `1400`-`1499`
+    Funicular,
+    /// `11`
+    TrolleyBus,
+    /// `12`
+    Monorail,
+    /// `200`-`299`
+    Coach,
+    /// `1100`-`1199`
+    Air,
+    /// `1500`-`1599`
+    Taxi,
+    /// Any other route type
+    Other(u16),
+}
+
+impl Serialize for KdiTransportEnum {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&format_args!("{:?}", self))
+    }
+}
+
+impl TryFrom<RouteType> for KdiTransportEnum {
+    type Error = RouteType;
+
+    fn try_from(route_type: RouteType) -> Result<Self, Self::Error> {
+        match route_type {
+            RouteType::Tramway => Ok(KdiTransportEnum::Tram),
+            RouteType::Subway => Ok(KdiTransportEnum::Subway),
+            RouteType::Rail => Ok(KdiTransportEnum::Train),
+            RouteType::Bus => Ok(KdiTransportEnum::Bus),
+            RouteType::Ferry => Ok(KdiTransportEnum::Ferry),
+            RouteType::CableCar => Ok(KdiTransportEnum::CableCar),
+            RouteType::Gondola => Ok(KdiTransportEnum::Gondola),
+            RouteType::Funicular => Ok(KdiTransportEnum::Funicular),
+            RouteType::Coach => Ok(KdiTransportEnum::Coach),
+            RouteType::Air => Ok(KdiTransportEnum::Air),
+            RouteType::Taxi => Ok(KdiTransportEnum::Taxi),
+            RouteType::Other(11) => Ok(KdiTransportEnum::TrolleyBus),
+            RouteType::Other(12) => Ok(KdiTransportEnum::Monorail),
+            RouteType::Other(300..=399) => Ok(KdiTransportEnum::Train),
+            RouteType::Other(500..=699) => Ok(KdiTransportEnum::Subway),
+            RouteType::Other(code) => u16::try_from(code)
+                .map(KdiTransportEnum::Other)
+                .map_err(|_| route_type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Transport mode of the raw `GTFS` `route_type` code, parsed as in a feed
+    fn transport(code: i32) -> KdiTransportEnum {
+        let route_type: RouteType = serde_json::from_value(serde_json::json!(code)).unwrap();
+        KdiTransportEnum::try_from(route_type).unwrap()
+    }
+
+    #[test]
+    fn basic_route_types() {
+        assert_eq!(transport(0), KdiTransportEnum::Tram);
+        assert_eq!(transport(3), KdiTransportEnum::Bus);
+        assert_eq!(transport(11), KdiTransportEnum::TrolleyBus);
+        assert_eq!(transport(12), KdiTransportEnum::Monorail);
+    }
+
+    #[test]
+    fn extended_route_types() {
+        assert_eq!(transport(100), KdiTransportEnum::Train);
+        assert_eq!(transport(300), KdiTransportEnum::Train);
+        assert_eq!(transport(399), KdiTransportEnum::Train);
+        assert_eq!(transport(500), KdiTransportEnum::Subway);
+        assert_eq!(transport(699), KdiTransportEnum::Subway);
+        assert_eq!(transport(1200), KdiTransportEnum::Ferry);
+    }
+
+    #[test]
+    fn unknown_route_type() {
+        let transport = transport(1700);
+        assert_eq!(transport, KdiTransportEnum::Other(1700));
+        assert_eq!(serde_json::to_value(&transport).unwrap(), "Other(1700)");
+    }
+
+    #[test]
+    fn out_of_range_route_type() {
+        let route_type = RouteType::Other(70_000);
+        assert_eq!(KdiTransportEnum::try_from(route_type), Err(route_type));
+    }
+}
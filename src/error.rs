@@ -0,0 +1,254 @@
+use gtfs_structures::RouteType;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::result::ZipError;
+
+/// Position of a record inside a data source
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    /// Source file
+    pub file: PathBuf,
+    /// Row or placemark index, when the source is ordered
+    pub index: Option<usize>,
+    /// Entity ID, when known
+    pub id: Option<String>,
+}
+
+impl Record {
+    pub fn new(file: &Path, index: Option<usize>, id: Option<&str>) -> Record {
+        Record {
+            file: file.to_path_buf(),
+            index,
+            id: id.map(str::to_string),
+        }
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}`", self.file.display())?;
+        if let Some(index) = self.index {
+            write!(f, " record #{}", index)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " (`{}`)", id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors raised while reading, aligning or writing the datasets
+#[derive(Debug)]
+pub enum KdiError {
+    /// I/O error on `file`
+    Io { file: PathBuf, source: io::Error },
+    /// Invalid zip archive or missing archive entry
+    Zip { file: PathBuf, source: ZipError },
+    /// Invalid `GTFS` feed
+    Gtfs {
+        file: PathBuf,
+        source: gtfs_structures::Error,
+    },
+    /// Invalid `CSV` row
+    Csv { file: PathBuf, source: csv::Error },
+    /// Generated `JSON Schema` of the `name` file that does not compile
+    InvalidSchema { name: String, reason: String },
+    /// Records of the `file` alignment directory that do not match their `JSON Schema`
+    SchemaViolations { file: PathBuf, count: usize },
+    /// Dangling references and duplicate keys found by the referential integrity check
+    IntegrityViolations { count: usize },
+    /// Inconsistent times, sequences and speeds found by the stop times check
+    StopTimeViolations { count: usize },
+    /// Exceptions outside of their calendar range and never active services found by the calendar check
+    CalendarViolations { count: usize },
+    /// `SQLite` database error
+    Sqlite {
+        file: PathBuf,
+        source: rusqlite::Error,
+    },
+    /// `Parquet` file error
+    Parquet {
+        file: PathBuf,
+        source: parquet::errors::ParquetError,
+    },
+    /// Invalid `XML` document
+    Xml {
+        file: PathBuf,
+        source: serde_xml_rs::Error,
+    },
+    /// Invalid `JSON` document or failed `JSON` serialization
+    Json {
+        file: PathBuf,
+        source: serde_json::Error,
+    },
+    /// Invalid `TOML` configuration
+    Toml {
+        file: PathBuf,
+        source: toml::de::Error,
+    },
+    /// Invalid `YAML` configuration
+    Yaml {
+        file: PathBuf,
+        source: serde_yaml::Error,
+    },
+    /// Configuration file with an unknown extension
+    UnknownConfigFormat { file: PathBuf },
+    /// Output `file` compression that its readers do not support
+    UnsupportedCompression {
+        file: PathBuf,
+        extension: &'static str,
+    },
+    /// Existing output directory with files in it
+    OutputNotEmpty { dir: PathBuf },
+    /// Alignment step whose result was requested before it ran
+    StepNotRun { step: &'static str },
+    /// `GTFS` feed without exactly one agency
+    AgencyCount { file: PathBuf, count: usize },
+    /// Required field missing from a record
+    MissingField { record: Record, field: &'static str },
+    /// Field that cannot be parsed
+    InvalidField {
+        record: Record,
+        field: &'static str,
+        value: String,
+    },
+    /// Coordinate that is not a `longitude,latitude[,altitude]` tuple
+    MalformedCoordinate { record: Record, value: String },
+    /// Reference to an entity that has not been aligned
+    DanglingReference {
+        record: Record,
+        field: &'static str,
+        value: String,
+    },
+    /// Route type outside of the `GTFS` route type range
+    UnknownRouteType {
+        record: Record,
+        route_type: RouteType,
+    },
+}
+
+impl Display for KdiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KdiError::Io { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Zip { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Gtfs { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Csv { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Sqlite { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Parquet { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::InvalidSchema { name, reason } => {
+                write!(f, "Invalid `{}` JSON Schema: {}", name, reason)
+            }
+            KdiError::SchemaViolations { file, count } => write!(
+                f,
+                "`{}`: {} records do not match their JSON Schema",
+                file.display(),
+                count
+            ),
+            KdiError::IntegrityViolations { count } => {
+                write!(f, "{} referential integrity errors", count)
+            }
+            KdiError::StopTimeViolations { count } => {
+                write!(f, "{} stop time consistency errors", count)
+            }
+            KdiError::CalendarViolations { count } => write!(f, "{} calendar errors", count),
+            KdiError::Xml { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Json { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Toml { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::Yaml { file, source } => write!(f, "`{}`: {}", file.display(), source),
+            KdiError::UnknownConfigFormat { file } => write!(
+                f,
+                "Unknown configuration format `{}`, expected `.toml`, `.yaml` or `.yml`",
+                file.display()
+            ),
+            KdiError::UnsupportedCompression { file, extension } => write!(
+                f,
+                "`{}`: `.{}` compression is not supported",
+                file.display(),
+                extension
+            ),
+            KdiError::OutputNotEmpty { dir } => write!(
+                f,
+                "`{}`: output directory is not empty, replace it with `--force`",
+                dir.display()
+            ),
+            KdiError::StepNotRun { step } => {
+                write!(f, "Alignment step `{}` has not been run", step)
+            }
+            KdiError::AgencyCount { file, count } => write!(
+                f,
+                "`{}`: expected exactly 1 agency, found {}",
+                file.display(),
+                count
+            ),
+            KdiError::MissingField { record, .. }
+            | KdiError::InvalidField { record, .. }
+            | KdiError::MalformedCoordinate { record, .. }
+            | KdiError::DanglingReference { record, .. }
+            | KdiError::UnknownRouteType { record, .. } => {
+                write!(f, "{}: {}", record, self.reason())
+            }
+        }
+    }
+}
+
+impl KdiError {
+    /// Record the error is tied to, if any
+    pub fn record(&self) -> Option<Record> {
+        match self {
+            KdiError::Csv { file, source } => source.position().map(|position| Record {
+                file: file.clone(),
+                index: Some(position.record() as usize),
+                id: None,
+            }),
+            KdiError::MissingField { record, .. }
+            | KdiError::InvalidField { record, .. }
+            | KdiError::MalformedCoordinate { record, .. }
+            | KdiError::DanglingReference { record, .. }
+            | KdiError::UnknownRouteType { record, .. } => Some(record.clone()),
+            _ => None,
+        }
+    }
+
+    /// Error description without the file or record position
+    pub fn reason(&self) -> String {
+        match self {
+            KdiError::MissingField { field, .. } => format!("missing field `{}`", field),
+            KdiError::InvalidField { field, value, .. } => {
+                format!("invalid field `{}` value `{}`", field, value)
+            }
+            KdiError::MalformedCoordinate { value, .. } => {
+                format!("malformed coordinate `{}`", value)
+            }
+            KdiError::DanglingReference { field, value, .. } => {
+                format!("field `{}` references unknown `{}`", field, value)
+            }
+            KdiError::UnknownRouteType { route_type, .. } => {
+                format!("invalid route type {:?}", route_type)
+            }
+            KdiError::Csv { source, .. } => source.to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Error for KdiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KdiError::Io { source, .. } => Some(source),
+            KdiError::Zip { source, .. } => Some(source),
+            KdiError::Gtfs { source, .. } => Some(source),
+            KdiError::Csv { source, .. } => Some(source),
+            KdiError::Sqlite { source, .. } => Some(source),
+            KdiError::Parquet { source, .. } => Some(source),
+            KdiError::Xml { source, .. } => Some(source),
+            KdiError::Json { source, .. } => Some(source),
+            KdiError::Toml { source, .. } => Some(source),
+            KdiError::Yaml { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::error::{KdiError, Record};
+use crate::structs::KdiLocation;
+
+/// Area the aligned locations are expected to lie in.
+/// Vertices are `[longitude, latitude]` pairs, as in `GeoJSON`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Area {
+    /// `[min longitude, min latitude, max longitude, max latitude]`
+    BoundingBox { bounding_box: [f64; 4] },
+    /// Vertices of a ring, closed or not
+    Polygon { polygon: Vec<[f64; 2]> },
+}
+
+impl Default for Area {
+    /// Trentino and the neighbouring areas reached by the extraurban services
+    fn default() -> Area {
+        Area::BoundingBox {
+            bounding_box: [10.0, 45.3, 12.3, 46.8],
+        }
+    }
+}
+
+impl FromStr for Area {
+    type Err = String;
+
+    /// Bounding box of a `min longitude,min latitude,max longitude,max latitude` string
+    fn from_str(value: &str) -> Result<Area, String> {
+        let bounds = value
+            .split(',')
+            .map(|bound| bound.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|error| format!("invalid bound in `{}`: {}", value, error))?;
+        let bounding_box: [f64; 4] = bounds.try_into().map_err(|_| {
+            format!(
+                "expected `min longitude,min latitude,max longitude,max latitude`, found `{}`",
+                value
+            )
+        })?;
+        let [min_longitude, min_latitude, max_longitude, max_latitude] = bounding_box;
+        if min_longitude > max_longitude || min_latitude > max_latitude {
+            return Err(format!("empty bounding box `{}`", value));
+        }
+
+        Ok(Area::BoundingBox { bounding_box })
+    }
+}
+
+impl Area {
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        match self {
+            Area::BoundingBox {
+                bounding_box: [min_longitude, min_latitude, max_longitude, max_latitude],
+            } => {
+                (*min_latitude..=*max_latitude).contains(&latitude)
+                    && (*min_longitude..=*max_longitude).contains(&longitude)
+            }
+            Area::Polygon { polygon: vertices } => {
+                // Ray casting towards growing longitudes
+                let mut inside = false;
+                let mut previous = match vertices.last() {
+                    Some(vertex) => vertex,
+                    None => return false,
+                };
+                for vertex in vertices {
+                    let ([x1, y1], [x2, y2]) = (previous, vertex);
+                    if (*y1 > latitude) != (*y2 > latitude)
+                        && longitude < x1 + (latitude - y1) / (y2 - y1) * (x2 - x1)
+                    {
+                        inside = !inside;
+                    }
+                    previous = vertex;
+                }
+                inside
+            }
+        }
+    }
+
+    /// Issue of the `(latitude, longitude)` coordinate, `None` when inside the area
+    pub fn check(&self, latitude: f64, longitude: f64) -> Option<CoordinateIssue> {
+        if self.contains(latitude, longitude) {
+            None
+        } else if latitude == 0.0 || longitude == 0.0 {
+            Some(CoordinateIssue::Zero)
+        } else if self.contains(longitude, latitude) {
+            Some(CoordinateIssue::Swapped)
+        } else {
+            Some(CoordinateIssue::Outside)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CoordinateIssue {
+    /// Latitude and longitude in place of each other, corrected by swapping them back
+    Swapped,
+    /// Missing coordinate stored as zero
+    Zero,
+    /// Coordinate outside of the area
+    Outside,
+}
+
+/// Location corrected or flagged by the [`GeographyCheck`], a record of the `location_issues` file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocationIssue {
+    pub file: PathBuf,
+    pub id: String,
+    pub issue: CoordinateIssue,
+    /// Coordinate of the source, before any correction
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Geographic sanity check of the aligned locations against an [`Area`].
+///
+/// Swapped coordinates are corrected, zero and outside ones are flagged and kept as is,
+/// since other entities may still reference them
+#[derive(Debug)]
+pub struct GeographyCheck<'a> {
+    area: &'a Area,
+    issues: Vec<LocationIssue>,
+}
+
+impl<'a> GeographyCheck<'a> {
+    pub fn new(area: &'a Area) -> GeographyCheck<'a> {
+        GeographyCheck {
+            area,
+            issues: Vec::new(),
+        }
+    }
+
+    /// Align the locations of the source at `path` with `align`,
+    /// appending them to `locations` once checked
+    pub fn align(
+        &mut self,
+        locations: &mut Vec<KdiLocation>,
+        path: &Path,
+        align: impl FnOnce(&mut Vec<KdiLocation>) -> Result<(), KdiError>,
+    ) -> Result<(), KdiError> {
+        let mut aligned = Vec::new();
+        align(&mut aligned)?;
+        self.locations(path, &mut aligned);
+        locations.append(&mut aligned);
+
+        Ok(())
+    }
+
+    /// Check the `locations` aligned from the source at `path`
+    pub fn locations(&mut self, path: &Path, locations: &mut [KdiLocation]) {
+        for location in locations {
+            let issue = match self.area.check(location.latitude, location.longitude) {
+                Some(issue) => issue,
+                None => continue,
+            };
+            let record = Record::new(path, None, Some(&location.id));
+            self.issues.push(LocationIssue {
+                file: path.to_path_buf(),
+                id: location.id.clone(),
+                issue,
+                latitude: location.latitude,
+                longitude: location.longitude,
+            });
+            match issue {
+                CoordinateIssue::Swapped => {
+                    info!(
+                        "{}: swapped latitude `{}` and longitude `{}`, corrected",
+                        record, location.latitude, location.longitude
+                    );
+                    std::mem::swap(&mut location.latitude, &mut location.longitude);
+                }
+                CoordinateIssue::Zero => warn!("{}: zero coordinate", record),
+                CoordinateIssue::Outside => warn!(
+                    "{}: coordinate `{},{}` outside of the area",
+                    record, location.longitude, location.latitude
+                ),
+            }
+        }
+    }
+
+    /// Report the number of corrected and flagged locations and return their issues
+    pub fn finish(self) -> Vec<LocationIssue> {
+        let corrected = self
+            .issues
+            .iter()
+            .filter(|issue| issue.issue == CoordinateIssue::Swapped)
+            .count();
+        info!(
+            "Geographic check: {} corrected, {} flagged locations",
+            corrected,
+            self.issues.len() - corrected
+        );
+
+        self.issues
+    }
+}
+
+/// Mean radius of the Earth, in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Great-circle distance in meters between two `(latitude, longitude)` coordinates
+pub fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (latitude_from, latitude_to) = (from.0.to_radians(), to.0.to_radians());
+    let latitude_delta = latitude_to - latitude_from;
+    let longitude_delta = (to.1 - from.1).to_radians();
+    let haversine = (latitude_delta / 2.0).sin().powi(2)
+        + latitude_from.cos() * latitude_to.cos() * (longitude_delta / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * haversine.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(id: &str, latitude: f64, longitude: f64) -> KdiLocation {
+        KdiLocation {
+            id: id.to_string(),
+            name: id.to_string(),
+            latitude,
+            longitude,
+        }
+    }
+
+    #[test]
+    fn bounding_box_from_str() {
+        assert_eq!(
+            "10.4, 45.6,12.0,46.6".parse(),
+            Ok(Area::BoundingBox {
+                bounding_box: [10.4, 45.6, 12.0, 46.6]
+            })
+        );
+        assert!("10.4,45.6,12.0".parse::<Area>().is_err());
+        assert!("10.4,45.6,12.0,north".parse::<Area>().is_err());
+        assert!("12.0,45.6,10.4,46.6".parse::<Area>().is_err());
+    }
+
+    #[test]
+    fn corrected_and_flagged_locations() {
+        let area = Area::default();
+        let mut check = GeographyCheck::new(&area);
+        let mut locations = vec![
+            location("inside", 46.07, 11.12),
+            location("swapped", 11.12, 46.07),
+            location("zero", 0.0, 0.0),
+            location("outside", 0.51, 0.06),
+        ];
+        check.locations(Path::new("stops.txt"), &mut locations);
+
+        assert_eq!(
+            (locations[1].latitude, locations[1].longitude),
+            (46.07, 11.12)
+        );
+        let issues = check.finish();
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| (issue.id.as_str(), issue.issue, issue.latitude))
+                .collect::<Vec<_>>(),
+            [
+                ("swapped", CoordinateIssue::Swapped, 11.12),
+                ("zero", CoordinateIssue::Zero, 0.0),
+                ("outside", CoordinateIssue::Outside, 0.51),
+            ]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use log::info;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::error::KdiError;
+use crate::output::{Compression, OutputFile};
+use crate::structs::KdiLocation;
+
+/// `GeoJSON` `FeatureCollection` file written one feature at a time
+pub struct GeoJsonWriter {
+    file: OutputFile,
+    empty: bool,
+}
+
+impl GeoJsonWriter {
+    /// Create the `name` layer file under `output`
+    pub fn create(
+        output: &Path,
+        name: &str,
+        compression: Compression,
+    ) -> Result<GeoJsonWriter, KdiError> {
+        let file_name = compression.file_name(&format!("{}.geojson", name));
+        info!("Writing `{}` file", file_name);
+        let mut writer = GeoJsonWriter {
+            file: OutputFile::create(&output.join(file_name), compression)?,
+            empty: true,
+        };
+        writer
+            .file
+            .write_raw(br#"{"type":"FeatureCollection","features":["#)?;
+
+        Ok(writer)
+    }
+
+    /// Write `entity` as a point feature at `location`, with the entity attributes as properties
+    pub fn write<T: Serialize>(
+        &mut self,
+        id: &str,
+        entity: &T,
+        location: &KdiLocation,
+    ) -> Result<(), KdiError> {
+        let properties = serde_json::to_value(entity).map_err(|source| KdiError::Json {
+            file: self.file.path().to_path_buf(),
+            source,
+        })?;
+        if !self.empty {
+            self.file.write_raw(b",")?;
+        }
+        self.empty = false;
+        let feature = feature(id, location, properties);
+        self.file.write_raw(feature.to_string().as_bytes())
+    }
+
+    pub fn finish(mut self) -> Result<(), KdiError> {
+        self.file.write_raw(b"]}")?;
+        self.file.finish()
+    }
+}
+
+/// Point feature, `GeoJSON` coordinates are `[longitude, latitude]`
+fn feature(id: &str, location: &KdiLocation, properties: Value) -> Value {
+    json!({
+        "type": "Feature",
+        "id": id,
+        "geometry": {
+            "type": "Point",
+            "coordinates": [location.longitude, location.latitude],
+        },
+        "properties": properties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn collection(output: &Path, name: &str) -> Value {
+        let content = fs::read(output.join(format!("{}.geojson", name))).unwrap();
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    #[test]
+    fn features() {
+        let output = tempfile::tempdir().unwrap();
+        let location = KdiLocation {
+            id: "L_1".to_string(),
+            name: "Piazza Dante".to_string(),
+            latitude: 46.07,
+            longitude: 11.12,
+        };
+        let mut writer = GeoJsonWriter::create(output.path(), "taxi", Compression::None).unwrap();
+        writer
+            .write("T_1", &json!({"type": "Taxi", "zone": null}), &location)
+            .unwrap();
+        writer
+            .write("T_2", &json!({"type": "Taxi"}), &location)
+            .unwrap();
+        writer.finish().unwrap();
+
+        let collection = collection(output.path(), "taxi");
+        assert_eq!(collection["type"], "FeatureCollection");
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(
+            features[0],
+            json!({
+                "type": "Feature",
+                "id": "T_1",
+                "geometry": {"type": "Point", "coordinates": [11.12, 46.07]},
+                "properties": {"type": "Taxi", "zone": null},
+            })
+        );
+        assert_eq!(features[1]["id"], "T_2");
+    }
+
+    #[test]
+    fn empty_layer() {
+        let output = tempfile::tempdir().unwrap();
+        GeoJsonWriter::create(output.path(), "bike_parking", Compression::None)
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        assert_eq!(
+            collection(output.path(), "bike_parking"),
+            json!({"type": "FeatureCollection", "features": []})
+        );
+    }
+}
@@ -0,0 +1,513 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use log::info;
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::enums::{
+    KdiDirectionEnum, KdiExceptionEnum, KdiPaymentEnum, KdiSupportedEnum, KdiTransportEnum,
+};
+use crate::error::{KdiError, Record};
+use crate::structs::{
+    KdiAgency, KdiCalendar, KdiCalendarException, KdiFare, KdiFareRule, KdiLocation,
+    KdiPublicTransportStop, KdiRoute, KdiStopTime, KdiTrip,
+};
+
+/// Format of the aligned date times, e.g. `2021-09-13T00:00:00`
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// `GTFS` feed archive written one file at a time
+pub struct GtfsWriter {
+    path: PathBuf,
+    zip: ZipWriter<BufWriter<File>>,
+}
+
+impl GtfsWriter {
+    /// Create the `name` feed archive under `output`
+    pub fn create(output: &Path, name: &str) -> Result<GtfsWriter, KdiError> {
+        let path = output.join(format!("{}.zip", name));
+        info!("Writing `{}` file", path.display());
+        let file = File::create(&path).map_err(|source| KdiError::Io {
+            file: path.clone(),
+            source,
+        })?;
+
+        Ok(GtfsWriter {
+            path,
+            zip: ZipWriter::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Write the `name` file of the feed, one row per record
+    pub fn write_file<T: Serialize>(
+        &mut self,
+        name: &str,
+        rows: impl IntoIterator<Item = Result<T, KdiError>>,
+    ) -> Result<(), KdiError> {
+        let mut file = self.file(name)?;
+        for row in rows {
+            file.write(&row?)?;
+        }
+        file.finish()
+    }
+
+    /// Writer of the `name` file of the feed, until the next file is started
+    pub fn file(&mut self, name: &str) -> Result<GtfsFile<'_>, KdiError> {
+        let path = self.path.join(name);
+        self.zip
+            .start_file(name, FileOptions::default())
+            .map_err(|source| KdiError::Zip {
+                file: path.clone(),
+                source,
+            })?;
+
+        Ok(GtfsFile {
+            path,
+            writer: csv::Writer::from_writer(&mut self.zip),
+        })
+    }
+
+    pub fn finish(mut self) -> Result<(), KdiError> {
+        self.zip.finish().map_err(|source| KdiError::Zip {
+            file: self.path,
+            source,
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Single `CSV` file of the feed archive
+pub struct GtfsFile<'a> {
+    path: PathBuf,
+    writer: csv::Writer<&'a mut ZipWriter<BufWriter<File>>>,
+}
+
+impl GtfsFile<'_> {
+    pub fn write<T: Serialize>(&mut self, row: &T) -> Result<(), KdiError> {
+        self.writer.serialize(row).map_err(|source| KdiError::Csv {
+            file: self.path.clone(),
+            source,
+        })
+    }
+
+    pub fn finish(mut self) -> Result<(), KdiError> {
+        self.writer.flush().map_err(|source| KdiError::Io {
+            file: self.path,
+            source,
+        })
+    }
+}
+
+/// `agency.txt` row
+#[derive(Serialize)]
+pub struct GtfsAgency<'a> {
+    agency_id: &'a str,
+    agency_name: &'a str,
+    agency_url: &'a str,
+    agency_timezone: &'a str,
+    agency_phone: &'a str,
+    agency_email: &'a str,
+}
+
+impl<'a> From<&'a KdiAgency<'a>> for GtfsAgency<'a> {
+    fn from(agency: &'a KdiAgency<'a>) -> Self {
+        GtfsAgency {
+            agency_id: agency.id,
+            agency_name: agency.name,
+            agency_url: agency.url,
+            agency_timezone: agency.timezone,
+            agency_phone: agency.phone,
+            agency_email: agency.email,
+        }
+    }
+}
+
+/// `stops.txt` row of a boarding stop.
+/// Fare zones are only `zone_id` values shared with the fare rules, they have no row of their own
+#[derive(Serialize)]
+pub struct GtfsStop<'a> {
+    stop_id: &'a str,
+    stop_name: &'a str,
+    stop_lat: f64,
+    stop_lon: f64,
+    zone_id: Option<&'a str>,
+    location_type: u8,
+    wheelchair_boarding: Option<u8>,
+}
+
+impl<'a> GtfsStop<'a> {
+    /// Boarding stop at its `location`
+    pub fn stop(stop: &'a KdiPublicTransportStop, location: &'a KdiLocation) -> Self {
+        GtfsStop {
+            stop_id: &stop.id,
+            stop_name: &location.name,
+            stop_lat: location.latitude,
+            stop_lon: location.longitude,
+            zone_id: stop.zone.as_deref(),
+            location_type: 0,
+            wheelchair_boarding: Some(supported(&stop.weelchair)),
+        }
+    }
+}
+
+/// `routes.txt` row
+#[derive(Serialize)]
+pub struct GtfsRoute<'a> {
+    route_id: &'a str,
+    agency_id: &'a str,
+    route_short_name: &'a str,
+    route_long_name: &'a str,
+    route_type: u16,
+}
+
+impl<'a> From<&'a KdiRoute<'a>> for GtfsRoute<'a> {
+    fn from(route: &'a KdiRoute<'a>) -> Self {
+        GtfsRoute {
+            route_id: &route.id,
+            agency_id: route.agency,
+            route_short_name: route.short_name,
+            route_long_name: route.long_name,
+            route_type: route_type(&route.transport),
+        }
+    }
+}
+
+/// `trips.txt` row
+#[derive(Serialize)]
+pub struct GtfsTrip<'a> {
+    route_id: &'a str,
+    service_id: &'a str,
+    trip_id: &'a str,
+    trip_headsign: &'a str,
+    direction_id: u8,
+    wheelchair_accessible: u8,
+    bikes_allowed: u8,
+}
+
+impl<'a> From<&'a KdiTrip<'a>> for GtfsTrip<'a> {
+    fn from(trip: &'a KdiTrip<'a>) -> Self {
+        GtfsTrip {
+            route_id: &trip.route,
+            service_id: &trip.calendar,
+            trip_id: &trip.id,
+            trip_headsign: trip.name,
+            direction_id: match trip.direction {
+                KdiDirectionEnum::Outbound => 0,
+                KdiDirectionEnum::Inbound => 1,
+            },
+            wheelchair_accessible: supported(&trip.weelchair),
+            bikes_allowed: supported(&trip.bike),
+        }
+    }
+}
+
+/// `stop_times.txt` row
+#[derive(Serialize)]
+pub struct GtfsStopTime<'a> {
+    trip_id: &'a str,
+    arrival_time: Option<String>,
+    departure_time: Option<String>,
+    stop_id: &'a str,
+    stop_sequence: usize,
+}
+
+impl<'a> TryFrom<&'a KdiStopTime> for GtfsStopTime<'a> {
+    type Error = KdiError;
+
+    fn try_from(stop_time: &'a KdiStopTime) -> Result<Self, Self::Error> {
+        let time = |field, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| {
+                    time(value).ok_or_else(|| invalid("stop_times", &stop_time.id, field, value))
+                })
+                .transpose()
+        };
+
+        Ok(GtfsStopTime {
+            trip_id: &stop_time.trip,
+            arrival_time: time("arrival", &stop_time.arrival)?,
+            departure_time: time("departure", &stop_time.departure)?,
+            stop_id: &stop_time.stop,
+            stop_sequence: stop_time.sequence,
+        })
+    }
+}
+
+/// `calendar.txt` row
+#[derive(Serialize)]
+pub struct GtfsCalendar<'a> {
+    service_id: &'a str,
+    monday: u8,
+    tuesday: u8,
+    wednesday: u8,
+    thursday: u8,
+    friday: u8,
+    saturday: u8,
+    sunday: u8,
+    start_date: String,
+    end_date: String,
+}
+
+impl<'a> TryFrom<&'a KdiCalendar> for GtfsCalendar<'a> {
+    type Error = KdiError;
+
+    fn try_from(calendar: &'a KdiCalendar) -> Result<Self, Self::Error> {
+        let date = |field, value: &str| {
+            date(value).ok_or_else(|| invalid("calendars", &calendar.id, field, value))
+        };
+
+        Ok(GtfsCalendar {
+            service_id: &calendar.id,
+            monday: u8::from(calendar.monday),
+            tuesday: u8::from(calendar.tuesday),
+            wednesday: u8::from(calendar.wednesday),
+            thursday: u8::from(calendar.thursday),
+            friday: u8::from(calendar.friday),
+            saturday: u8::from(calendar.saturday),
+            sunday: u8::from(calendar.sunday),
+            start_date: date("start_date", &calendar.start_date)?,
+            end_date: date("end_date", &calendar.end_date)?,
+        })
+    }
+}
+
+/// `calendar_dates.txt` row
+#[derive(Serialize)]
+pub struct GtfsCalendarDate<'a> {
+    service_id: &'a str,
+    date: String,
+    exception_type: u8,
+}
+
+impl<'a> TryFrom<&'a KdiCalendarException> for GtfsCalendarDate<'a> {
+    type Error = KdiError;
+
+    fn try_from(exception: &'a KdiCalendarException) -> Result<Self, Self::Error> {
+        Ok(GtfsCalendarDate {
+            service_id: &exception.calendar,
+            date: date(&exception.date).ok_or_else(|| {
+                invalid(
+                    "calendar_exceptions",
+                    &exception.id,
+                    "date",
+                    &exception.date,
+                )
+            })?,
+            exception_type: match exception.exception {
+                KdiExceptionEnum::Added => 1,
+                KdiExceptionEnum::Removed => 2,
+            },
+        })
+    }
+}
+
+/// `fare_attributes.txt` row
+#[derive(Serialize)]
+pub struct GtfsFareAttribute<'a> {
+    fare_id: &'a str,
+    price: f64,
+    currency_type: String,
+    payment_method: u8,
+    /// Empty, unlimited transfers
+    transfers: Option<u8>,
+    transfer_duration: usize,
+}
+
+impl<'a> From<&'a KdiFare> for GtfsFareAttribute<'a> {
+    fn from(fare: &'a KdiFare) -> Self {
+        GtfsFareAttribute {
+            fare_id: &fare.id,
+            price: fare.price,
+            currency_type: format!("{:?}", fare.currency),
+            payment_method: match fare.payment {
+                KdiPaymentEnum::OnBoard => 0,
+                KdiPaymentEnum::BeforeBoarding => 1,
+            },
+            transfers: None,
+            transfer_duration: fare.duration,
+        }
+    }
+}
+
+/// `fare_rules.txt` row
+#[derive(Serialize)]
+pub struct GtfsFareRule {
+    fare_id: String,
+    route_id: Option<String>,
+    origin_id: Option<String>,
+    destination_id: Option<String>,
+    contains_id: Option<String>,
+}
+
+impl From<KdiFareRule> for GtfsFareRule {
+    fn from(fare_rule: KdiFareRule) -> Self {
+        GtfsFareRule {
+            fare_id: fare_rule.fare,
+            route_id: fare_rule.route,
+            origin_id: fare_rule.origin,
+            destination_id: fare_rule.destination,
+            contains_id: fare_rule.contains,
+        }
+    }
+}
+
+/// Basic `GTFS` route type of `transport`, the extended one when there is none
+fn route_type(transport: &KdiTransportEnum) -> u16 {
+    match transport {
+        KdiTransportEnum::Tram => 0,
+        KdiTransportEnum::Subway => 1,
+        KdiTransportEnum::Train => 2,
+        KdiTransportEnum::Bus => 3,
+        KdiTransportEnum::Ferry => 4,
+        KdiTransportEnum::CableCar => 5,
+        KdiTransportEnum::Gondola => 6,
+        KdiTransportEnum::Funicular => 7,
+        KdiTransportEnum::TrolleyBus => 11,
+        KdiTransportEnum::Monorail => 12,
+        KdiTransportEnum::Coach => 200,
+        KdiTransportEnum::Air => 1100,
+        KdiTransportEnum::Taxi => 1500,
+        KdiTransportEnum::Other(route_type) => *route_type,
+    }
+}
+
+fn supported(supported: &KdiSupportedEnum) -> u8 {
+    match supported {
+        KdiSupportedEnum::Unknown => 0,
+        KdiSupportedEnum::Supported => 1,
+        KdiSupportedEnum::NotSupported => 2,
+    }
+}
+
+/// `YYYYMMDD` date of an aligned date time
+fn date(value: &str) -> Option<String> {
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .ok()
+        .map(|date_time| date_time.format("%Y%m%d").to_string())
+}
+
+/// `HH:MM:SS` time of an aligned stop time.
+/// Days after the first one are added back to the hours, e.g. `0000-01-02T01:10:00` is `25:10:00`
+fn time(value: &str) -> Option<String> {
+    let date_time = NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT).ok()?;
+    let hours = date_time.date().ordinal0() * 24 + date_time.hour();
+
+    Some(format!(
+        "{:02}:{:02}:{:02}",
+        hours,
+        date_time.minute(),
+        date_time.second()
+    ))
+}
+
+fn invalid(file: &str, id: &str, field: &'static str, value: &str) -> KdiError {
+    KdiError::InvalidField {
+        record: Record::new(Path::new(file), None, Some(id)),
+        field,
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::align::TT;
+    use crate::alignment::{Alignment, Group};
+    use crate::config::{Config, Source};
+    use crate::source::Sources;
+    use gtfs_structures::{Gtfs, RouteType};
+    use std::io::Read;
+
+    #[test]
+    fn times_past_midnight() {
+        assert_eq!(time("0000-01-01T08:05:03").as_deref(), Some("08:05:03"));
+        assert_eq!(time("0000-01-02T01:10:00").as_deref(), Some("25:10:00"));
+        assert_eq!(time("0000-01-03T00:00:00").as_deref(), Some("48:00:00"));
+        assert_eq!(time("25:10:00"), None);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date("2021-09-13T00:00:00").as_deref(), Some("20210913"));
+        assert_eq!(date("2021-09-13"), None);
+    }
+
+    #[test]
+    fn route_types_round_trip() {
+        let transports = [
+            KdiTransportEnum::Train,
+            KdiTransportEnum::Bus,
+            KdiTransportEnum::CableCar,
+            KdiTransportEnum::Tram,
+            KdiTransportEnum::Subway,
+            KdiTransportEnum::Ferry,
+            KdiTransportEnum::Gondola,
+            KdiTransportEnum::Funicular,
+            KdiTransportEnum::TrolleyBus,
+            KdiTransportEnum::Monorail,
+            KdiTransportEnum::Coach,
+            KdiTransportEnum::Air,
+            KdiTransportEnum::Taxi,
+            KdiTransportEnum::Other(1700),
+        ];
+        for transport in transports {
+            let code = route_type(&transport);
+            let parsed: RouteType = serde_json::from_value(serde_json::json!(code)).unwrap();
+            assert_eq!(KdiTransportEnum::try_from(parsed), Ok(transport));
+        }
+    }
+
+    #[test]
+    fn merged_feed() {
+        let data = |file: &str| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("data")
+                .join(file)
+        };
+        let config = Config {
+            sources: vec![
+                Source::Gtfs {
+                    path: data("urban.zip"),
+                    tt: TT::Urban,
+                },
+                Source::Fare {
+                    path: data("urban_fare.zip"),
+                    tt: TT::Urban,
+                },
+            ],
+            area: Default::default(),
+        };
+        let sources = Sources::read(&config).unwrap();
+        let alignment = Alignment::align(&sources, &[Group::Common, Group::Core], true).unwrap();
+        let output = tempfile::tempdir().unwrap();
+        alignment.write_gtfs(output.path()).unwrap();
+
+        let path = output.path().join("gtfs.zip");
+        let feed = Gtfs::new(&path.to_string_lossy()).unwrap();
+        assert_eq!(feed.agencies.len(), alignment.agencies.len());
+        assert_eq!(feed.routes.len(), alignment.routes.len());
+        assert_eq!(feed.trips.len(), alignment.trips.len());
+        assert_eq!(feed.calendar.len(), alignment.calendars.len());
+        assert_eq!(feed.fare_attributes.len(), alignment.fares.len());
+        let stop_times: usize = feed.trips.values().map(|trip| trip.stop_times.len()).sum();
+        assert_eq!(stop_times, alignment.stop_times.len());
+        // Only public transport stops, with the prefixed IDs of the alignment
+        assert_eq!(feed.stops.len(), alignment.public_transport_stops.len());
+        assert!(feed.stops.keys().all(|id| id.starts_with("U_")));
+        assert!(feed.trips.keys().all(|id| id.starts_with("U_")));
+
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut fare_rules = String::new();
+        archive
+            .by_name("fare_rules.txt")
+            .unwrap()
+            .read_to_string(&mut fare_rules)
+            .unwrap();
+        assert_eq!(fare_rules.lines().count(), alignment.fare_rule_count + 1);
+    }
+}
@@ -0,0 +1,525 @@
+use log::{debug, info, warn};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{KdiError, Record};
+use crate::schema::{self, FileName};
+
+/// Entity file of an alignment, with the fields identifying its records and their references
+#[derive(Debug)]
+pub struct EntityFile {
+    pub name: &'static str,
+    /// Fields whose values identify a record
+    pub key: &'static [&'static str],
+    /// Fields holding the ID of a record of another file
+    pub references: &'static [Reference],
+}
+
+impl EntityFile {
+    const fn new(name: &'static str, references: &'static [Reference]) -> EntityFile {
+        EntityFile {
+            name,
+            key: &["id"],
+            references,
+        }
+    }
+}
+
+/// Field referencing the records of the `target` file whose ID starts with `prefix`
+#[derive(Debug)]
+pub struct Reference {
+    pub field: &'static str,
+    pub target: &'static str,
+    pub prefix: &'static str,
+}
+
+const fn reference(field: &'static str, target: &'static str) -> Reference {
+    Reference {
+        field,
+        target,
+        prefix: "",
+    }
+}
+
+/// Reference to a fare zone, a location whose ID starts with `ZONE_`
+const fn zone(field: &'static str) -> Reference {
+    Reference {
+        field,
+        target: "locations",
+        prefix: "ZONE_",
+    }
+}
+
+// --- COMMON
+pub const LOCATIONS: EntityFile = EntityFile::new("locations", &[]);
+pub const CALENDARS: EntityFile = EntityFile::new("calendars", &[]);
+pub const AGENCIES: EntityFile = EntityFile::new("agencies", &[]);
+pub const CALENDAR_EXCEPTIONS: EntityFile =
+    EntityFile::new("calendar_exceptions", &[reference("calendar", "calendars")]);
+
+// --- CORE
+pub const FARES: EntityFile = EntityFile::new("fares", &[]);
+pub const ROUTES: EntityFile = EntityFile::new("routes", &[reference("agency", "agencies")]);
+pub const TRIPS: EntityFile = EntityFile::new(
+    "trips",
+    &[
+        reference("route", "routes"),
+        reference("calendar", "calendars"),
+    ],
+);
+pub const PUBLIC_TRANSPORT_STOPS: EntityFile = EntityFile::new(
+    "public_transport_stops",
+    &[reference("location", "locations"), zone("zone")],
+);
+pub const PARKING_STOPS: EntityFile =
+    EntityFile::new("parking_stops", &[reference("location", "locations")]);
+pub const BIKE_SHARING_STOPS: EntityFile =
+    EntityFile::new("bike_sharing_stops", &[reference("location", "locations")]);
+pub const STOP_TIMES: EntityFile = EntityFile {
+    // A trip may pass through the same stop more than once, the ID is not unique
+    key: &["trip", "sequence"],
+    ..EntityFile::new(
+        "stop_times",
+        &[
+            reference("trip", "trips"),
+            reference("stop", "public_transport_stops"),
+        ],
+    )
+};
+pub const FARE_RULES: EntityFile = EntityFile::new(
+    "fare_rules",
+    &[
+        reference("fare", "fares"),
+        reference("route", "routes"),
+        zone("origin"),
+        zone("destination"),
+        zone("contains"),
+    ],
+);
+
+/// Entity files, each after the files it references
+pub const FILES: [&EntityFile; 12] = [
+    &LOCATIONS,
+    &CALENDARS,
+    &AGENCIES,
+    &FARES,
+    &ROUTES,
+    &TRIPS,
+    &PUBLIC_TRANSPORT_STOPS,
+    &PARKING_STOPS,
+    &BIKE_SHARING_STOPS,
+    &CALENDAR_EXCEPTIONS,
+    &STOP_TIMES,
+    &FARE_RULES,
+];
+
+/// Referential integrity check of the records of the entity files, read in the [`FILES`] order.
+///
+/// Dangling references and records sharing a key with a different record are errors,
+/// records that are never referenced and identical duplicates are warnings
+#[derive(Debug, Default)]
+pub struct IntegrityCheck {
+    /// IDs of the records of the referenced files
+    ids: HashMap<&'static str, HashSet<String>>,
+    /// IDs referenced by the records read so far, by referenced file
+    referenced: HashMap<&'static str, HashSet<String>>,
+    /// Spooled record of every key, by file and key hash.
+    /// Records are only read back when another record has the same key hash
+    keys: HashMap<&'static str, HashMap<u64, SpooledRecord>>,
+    /// Spooled record of the keys whose hash is already taken by another key, by file
+    collisions: HashMap<&'static str, HashMap<Vec<Option<String>>, SpooledRecord>>,
+    /// Serialized records, created with the first record
+    spool: Option<RecordSpool>,
+    /// First error of the record spool, returned by [`IntegrityCheck::finish`]
+    error: Option<KdiError>,
+    /// Number of identical duplicate records, by file
+    duplicates: HashMap<&'static str, usize>,
+    file: Option<(&'static EntityFile, PathBuf)>,
+    index: usize,
+    errors: usize,
+    warnings: usize,
+}
+
+impl IntegrityCheck {
+    pub fn new() -> IntegrityCheck {
+        IntegrityCheck::default()
+    }
+
+    /// Start the records of the `entity` file stored at `path`
+    pub fn file(&mut self, entity: &'static EntityFile, path: &Path) {
+        debug!("Checking `{}` references", path.display());
+        if is_referenced(entity) {
+            self.ids.entry(entity.name).or_default();
+        }
+        for reference in entity.references {
+            self.referenced.entry(reference.target).or_default();
+        }
+        self.keys.entry(entity.name).or_default();
+        self.file = Some((entity, path.to_path_buf()));
+        self.index = 0;
+    }
+
+    /// Check the next record of the current file
+    pub fn record(&mut self, record: &Value) {
+        if self.error.is_some() {
+            return;
+        }
+        let (entity, path) = match &self.file {
+            Some((entity, path)) => (*entity, path),
+            None => return,
+        };
+        let id = field(record, "id");
+        let position = Record::new(path, Some(self.index), id.as_deref());
+        self.index += 1;
+
+        // --- DUPLICATES
+        let previous = match self.previous(entity, record) {
+            Ok(previous) => previous,
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        };
+        match previous {
+            // Already checked
+            Some(true) => {
+                debug!("{}: identical duplicate record", position);
+                *self.duplicates.entry(entity.name).or_default() += 1;
+                return;
+            }
+            Some(false) => {
+                warn!("{}: duplicate `{}`", position, entity.key.join("`, `"));
+                self.errors += 1;
+            }
+            None => {}
+        }
+        if let (Some(ids), Some(id)) = (self.ids.get_mut(entity.name), id) {
+            ids.insert(id);
+        }
+
+        // --- REFERENCES
+        for reference in entity.references {
+            let value = match field(record, reference.field) {
+                Some(value) => value,
+                // Optional reference
+                None => continue,
+            };
+            let ids = match self.ids.get(reference.target) {
+                Some(ids) => ids,
+                // Referenced file not part of the alignment
+                None => continue,
+            };
+            if !value.starts_with(reference.prefix) || !ids.contains(&value) {
+                warn!(
+                    "{} in `{}`",
+                    KdiError::DanglingReference {
+                        record: position.clone(),
+                        field: reference.field,
+                        value: value.clone(),
+                    },
+                    reference.target
+                );
+                self.errors += 1;
+            }
+            if let Some(referenced) = self.referenced.get_mut(reference.target) {
+                referenced.insert(value);
+            }
+        }
+    }
+
+    /// Whether the record previously read with the key of `record` is identical to it,
+    /// `None` for the first record of the key
+    fn previous(
+        &mut self,
+        entity: &'static EntityFile,
+        record: &Value,
+    ) -> Result<Option<bool>, KdiError> {
+        // Objects are sorted by field name, the serialization does not depend on the field order
+        let key = record_key(entity, record);
+        let serialized = record.to_string();
+        let spool = match &mut self.spool {
+            Some(spool) => spool,
+            None => self.spool.insert(RecordSpool::new()?),
+        };
+        let stored = match self.keys.entry(entity.name).or_default().entry(hash(&key)) {
+            Entry::Vacant(entry) => {
+                entry.insert(spool.push(&serialized)?);
+                return Ok(None);
+            }
+            Entry::Occupied(entry) => spool.read(*entry.get())?,
+        };
+        if stored == serialized {
+            return Ok(Some(true));
+        }
+        let stored: Value = serde_json::from_str(&stored).unwrap_or_default();
+        if record_key(entity, &stored) == key {
+            return Ok(Some(false));
+        }
+
+        // Different keys with the same hash
+        match self.collisions.entry(entity.name).or_default().entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(spool.push(&serialized)?);
+                Ok(None)
+            }
+            Entry::Occupied(entry) => Ok(Some(spool.read(*entry.get())? == serialized)),
+        }
+    }
+
+    /// Check the `entities` as the records of the `entity` file
+    pub fn records<'e, T: Serialize + 'e>(
+        &mut self,
+        entity: &'static EntityFile,
+        entities: impl IntoIterator<Item = &'e T>,
+    ) -> Result<(), KdiError> {
+        let path = Path::new(entity.name);
+        self.file(entity, path);
+        for entity in entities {
+            let record = serde_json::to_value(entity).map_err(|source| KdiError::Json {
+                file: path.to_path_buf(),
+                source,
+            })?;
+            self.record(&record);
+        }
+
+        Ok(())
+    }
+
+    /// Report the records never referenced, failing when any error has been found
+    pub fn finish(mut self) -> Result<(), KdiError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        let mut duplicates: Vec<_> = self.duplicates.iter().collect();
+        duplicates.sort();
+        for (name, count) in duplicates {
+            warn!("`{}`: {} identical duplicate records", name, count);
+            self.warnings += count;
+        }
+        let mut files: Vec<_> = self.ids.iter().collect();
+        files.sort_by_key(|(name, _)| **name);
+        for (name, ids) in files {
+            let referenced = match self.referenced.get(name) {
+                Some(referenced) => referenced,
+                // No file referencing them is part of the alignment
+                None => continue,
+            };
+            let mut orphans: Vec<&String> = ids.difference(referenced).collect();
+            orphans.sort();
+            for id in &orphans {
+                warn!("`{}` record `{}` is never referenced", name, id);
+            }
+            self.warnings += orphans.len();
+        }
+
+        info!(
+            "Referential integrity: {} errors, {} warnings",
+            self.errors, self.warnings
+        );
+        if self.errors > 0 {
+            return Err(KdiError::IntegrityViolations { count: self.errors });
+        }
+
+        Ok(())
+    }
+}
+
+/// Check the referential integrity of the `JSON` and `JSON Lines` entity files of the `input` directory,
+/// compressed and chunked ones included
+pub fn check(input: &Path) -> Result<(), KdiError> {
+    let paths = schema::alignment_files(input)?;
+    let mut check = IntegrityCheck::new();
+    for entity in FILES {
+        let files: Vec<(&PathBuf, FileName)> = paths
+            .iter()
+            .filter_map(|path| Some((path, FileName::parse(path)?)))
+            .filter(|(_, file)| file.matches(entity.name))
+            .collect();
+        // Chunks are copies of the records of the whole file, read only without it
+        let whole = files.iter().any(|(_, file)| !file.is_chunk_of(entity.name));
+        for (path, file) in files {
+            if whole && file.is_chunk_of(entity.name) {
+                continue;
+            }
+            info!("Checking `{}`", path.display());
+            check.file(entity, path);
+            schema::read_records(path, &file, &mut |record| check.record(&record))?;
+        }
+    }
+
+    check.finish()
+}
+
+/// Whether the records of `entity` are referenced by another file
+fn is_referenced(entity: &EntityFile) -> bool {
+    FILES.iter().any(|file| {
+        file.references
+            .iter()
+            .any(|reference| reference.target == entity.name)
+    })
+}
+
+/// Value of the `name` field of `record` as a string, `None` when missing or null
+fn field(record: &Value, name: &str) -> Option<String> {
+    match record.get(name)? {
+        Value::Null => None,
+        Value::String(value) => Some(value.clone()),
+        value => Some(value.to_string()),
+    }
+}
+
+fn record_key(entity: &EntityFile, record: &Value) -> Vec<Option<String>> {
+    entity.key.iter().map(|name| field(record, name)).collect()
+}
+
+/// Records serialized to an unnamed temporary file, removed when dropped
+#[derive(Debug)]
+struct RecordSpool {
+    file: BufWriter<File>,
+    len: u64,
+}
+
+/// Position of a record in the [`RecordSpool`]
+#[derive(Debug, Clone, Copy)]
+struct SpooledRecord {
+    offset: u64,
+    len: usize,
+}
+
+impl RecordSpool {
+    fn new() -> Result<RecordSpool, KdiError> {
+        let file = tempfile::tempfile().map_err(spool_error)?;
+        Ok(RecordSpool {
+            file: BufWriter::new(file),
+            len: 0,
+        })
+    }
+
+    fn push(&mut self, serialized: &str) -> Result<SpooledRecord, KdiError> {
+        self.file
+            .write_all(serialized.as_bytes())
+            .map_err(spool_error)?;
+        let record = SpooledRecord {
+            offset: self.len,
+            len: serialized.len(),
+        };
+        self.len += serialized.len() as u64;
+
+        Ok(record)
+    }
+
+    fn read(&mut self, record: SpooledRecord) -> Result<String, KdiError> {
+        let mut bytes = vec![0; record.len];
+        self.file
+            .flush()
+            .and_then(|()| {
+                let file = self.file.get_mut();
+                file.seek(SeekFrom::Start(record.offset))?;
+                file.read_exact(&mut bytes)?;
+                file.seek(SeekFrom::End(0))
+            })
+            .map_err(spool_error)?;
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+fn spool_error(source: io::Error) -> KdiError {
+    KdiError::Io {
+        file: std::env::temp_dir(),
+        source,
+    }
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Result, errors and warnings of the check of the `records` of the `calendars` file
+    fn calendars(records: &[Value]) -> (bool, usize, usize) {
+        let mut check = IntegrityCheck::new();
+        check.file(&CALENDARS, Path::new("calendars.json"));
+        for record in records {
+            check.record(record);
+        }
+        let (errors, warnings) = (check.errors, check.duplicates.values().sum());
+        (check.finish().is_ok(), errors, warnings)
+    }
+
+    #[test]
+    fn distinct_keys() {
+        let records = [
+            json!({ "id": "a", "monday": true }),
+            json!({ "id": "b", "monday": true }),
+        ];
+        assert_eq!(calendars(&records), (true, 0, 0));
+    }
+
+    #[test]
+    fn identical_duplicate() {
+        let records = [
+            json!({ "id": "a", "monday": true }),
+            json!({ "id": "a", "monday": true }),
+        ];
+        assert_eq!(calendars(&records), (true, 0, 1));
+    }
+
+    #[test]
+    fn conflicting_duplicate() {
+        let records = [
+            json!({ "id": "a", "monday": true }),
+            json!({ "id": "a", "monday": false }),
+        ];
+        assert_eq!(calendars(&records), (false, 1, 0));
+    }
+
+    #[test]
+    fn reordered_duplicate() {
+        let records = [
+            serde_json::from_str(r#"{ "id": "a", "monday": true, "tuesday": false }"#).unwrap(),
+            serde_json::from_str(r#"{ "tuesday": false, "id": "a", "monday": true }"#).unwrap(),
+        ];
+        assert_eq!(calendars(&records), (true, 0, 1));
+    }
+
+    #[test]
+    fn key_hash_collision() {
+        let mut check = IntegrityCheck::new();
+        check.file(&CALENDARS, Path::new("calendars.json"));
+        check.record(&json!({ "id": "a", "monday": true }));
+        // Record of the `a` key moved under the hash of the `b` key
+        let keys = check.keys.get_mut("calendars").unwrap();
+        let a = keys.remove(&hash(&vec![Some("a".to_string())])).unwrap();
+        keys.insert(hash(&vec![Some("b".to_string())]), a);
+        check.record(&json!({ "id": "b", "monday": true }));
+        assert_eq!((check.errors, check.duplicates.len()), (0, 0));
+        check.record(&json!({ "id": "b", "monday": false }));
+        assert_eq!(check.errors, 1);
+    }
+
+    #[test]
+    fn composite_key() {
+        let stop_time = |trip: &str, sequence: usize| json!({ "id": format!("{}_stop", trip), "trip": trip, "stop": "stop", "sequence": sequence });
+        let mut check = IntegrityCheck::new();
+        check.file(&STOP_TIMES, Path::new("stop_times.json"));
+        check.record(&stop_time("trip", 1));
+        check.record(&stop_time("trip", 2));
+        check.record(&stop_time("other", 1));
+        assert_eq!(check.errors, 0);
+        check.record(&json!({ "id": "trip_stop", "trip": "trip", "stop": "other", "sequence": 2 }));
+        assert_eq!(check.errors, 1);
+    }
+}
@@ -1,5 +0,0 @@
-pub mod align;
-pub mod enums;
-pub mod json;
-pub mod kml;
-pub mod structs;
@@ -0,0 +1,324 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::error::KdiError;
+use crate::output::{Compression, OutputFile};
+use crate::structs::KdiLocation;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename(deserialize = "kml"))]
+pub struct Kml {
+    #[serde(rename(deserialize = "Document"))]
+    pub document: Document,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Document {
+    #[serde(rename(deserialize = "Folder"))]
+    pub folder: Folder,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Folder {
+    pub name: String,
+    #[serde(rename = "Placemark")]
+    pub placemarks: Vec<Placemark>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Placemark {
+    #[serde(rename = "Point")]
+    pub point: Point,
+    #[serde(rename = "ExtendedData")]
+    pub extended_data: ExtendedData,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Point {
+    pub coordinates: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExtendedData {
+    #[serde(rename = "SchemaData")]
+    pub schema_data: SchemaData,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SchemaData {
+    #[serde(rename = "SimpleData")]
+    pub simple_datas: Vec<SimpleData>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SimpleData {
+    pub name: String,
+    #[serde(rename = "$value")]
+    pub value: String,
+}
+
+/// `KML` document written one element at a time, either as a plain `.kml` file
+/// or as the `doc.kml` entry of a `.kmz` archive
+pub struct KmlWriter {
+    path: PathBuf,
+    sink: Sink,
+}
+
+enum Sink {
+    Kml(OutputFile),
+    Kmz(ZipWriter<BufWriter<File>>),
+}
+
+/// `KML` output container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KmlFormat {
+    /// Plain document, compressed with the output compression
+    Kml,
+    /// Zipped document
+    Kmz,
+}
+
+impl KmlWriter {
+    /// Create the `name` document under `output`
+    pub fn create(
+        output: &Path,
+        name: &str,
+        format: KmlFormat,
+        compression: Compression,
+    ) -> Result<KmlWriter, KdiError> {
+        let mut writer = match format {
+            KmlFormat::Kml => {
+                let file_name = compression.file_name(&format!("{}.kml", name));
+                info!("Writing `{}` file", file_name);
+                let path = output.join(file_name);
+                KmlWriter {
+                    sink: Sink::Kml(OutputFile::create(&path, compression)?),
+                    path,
+                }
+            }
+            KmlFormat::Kmz => {
+                let path = output.join(format!("{}.kmz", name));
+                info!("Writing `{}` file", path.display());
+                let file = File::create(&path).map_err(|source| KdiError::Io {
+                    file: path.clone(),
+                    source,
+                })?;
+                let mut zip = ZipWriter::new(BufWriter::new(file));
+                zip.start_file("doc.kml", FileOptions::default())
+                    .map_err(|source| KdiError::Zip {
+                        file: path.clone(),
+                        source,
+                    })?;
+                KmlWriter {
+                    sink: Sink::Kmz(zip),
+                    path,
+                }
+            }
+        };
+        writer.write_raw(&format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><kml xmlns="http://www.opengis.net/kml/2.2"><Document><name>{}</name>"#,
+            escape(name)
+        ))?;
+
+        Ok(writer)
+    }
+
+    /// Shared `id` style of the placemarks, an icon tinted with the `aabbggrr` `color`
+    pub fn style(&mut self, id: &str, color: &str) -> Result<(), KdiError> {
+        self.write_raw(&format!(
+            r#"<Style id="{}"><IconStyle><color>{}</color><Icon><href>{}</href></Icon></IconStyle></Style>"#,
+            escape(id),
+            color,
+            ICON
+        ))
+    }
+
+    /// Open the `name` folder, the following placemarks and folders are nested in it until it is closed
+    pub fn open_folder(&mut self, name: &str) -> Result<(), KdiError> {
+        self.write_raw(&format!("<Folder><name>{}</name>", escape(name)))
+    }
+
+    pub fn close_folder(&mut self) -> Result<(), KdiError> {
+        self.write_raw("</Folder>")
+    }
+
+    /// Write `entity` as a `style` placemark at `location`, with the entity attributes as extended data
+    pub fn write<T: Serialize>(
+        &mut self,
+        entity: &T,
+        style: &str,
+        location: &KdiLocation,
+    ) -> Result<(), KdiError> {
+        let attributes = serde_json::to_value(entity).map_err(|source| KdiError::Json {
+            file: self.path.clone(),
+            source,
+        })?;
+        let mut placemark = format!(
+            "<Placemark><name>{}</name><styleUrl>#{}</styleUrl><ExtendedData>",
+            escape(&location.name),
+            escape(style)
+        );
+        if let serde_json::Value::Object(attributes) = attributes {
+            for (name, value) in attributes {
+                let value = match value {
+                    serde_json::Value::Null => continue,
+                    serde_json::Value::String(value) => value,
+                    value => value.to_string(),
+                };
+                placemark.push_str(&format!(
+                    r#"<Data name="{}"><value>{}</value></Data>"#,
+                    escape(&name),
+                    escape(&value)
+                ));
+            }
+        }
+        // `KML` coordinates are `longitude,latitude`
+        placemark.push_str(&format!(
+            "</ExtendedData><Point><coordinates>{},{}</coordinates></Point></Placemark>",
+            location.longitude, location.latitude
+        ));
+        self.write_raw(&placemark)
+    }
+
+    pub fn finish(mut self) -> Result<(), KdiError> {
+        self.write_raw("</Document></kml>")?;
+        match self.sink {
+            Sink::Kml(file) => file.finish(),
+            Sink::Kmz(mut zip) => {
+                zip.finish().map_err(|source| KdiError::Zip {
+                    file: self.path,
+                    source,
+                })?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_raw(&mut self, text: &str) -> Result<(), KdiError> {
+        match &mut self.sink {
+            Sink::Kml(file) => file.write_raw(text.as_bytes()),
+            Sink::Kmz(zip) => zip
+                .write_all(text.as_bytes())
+                .map_err(|source| KdiError::Io {
+                    file: self.path.clone(),
+                    source,
+                }),
+        }
+    }
+}
+
+/// Colors (`aabbggrr`) assigned in turn to the styles
+pub const COLORS: [&str; 8] = [
+    "ff0000ff", "ff00a5ff", "ff00ffff", "ff00ff00", "ffffff00", "ffff0000", "ffff00ff", "ff800080",
+];
+
+/// Icon of every style, tinted by the style color
+const ICON: &str = "http://maps.google.com/mapfiles/kml/paddle/wht-blank.png";
+
+/// Escape the `XML` special characters of `text`
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn location() -> KdiLocation {
+        KdiLocation {
+            id: "L_1".to_string(),
+            name: "Bar \"Dante\" & <Caffè>".to_string(),
+            latitude: 46.07,
+            longitude: 11.12,
+        }
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            escape(r#"A & B <c> "d" 'e'"#),
+            "A &amp; B &lt;c&gt; &quot;d&quot; &apos;e&apos;"
+        );
+        assert_eq!(escape("Caffè"), "Caffè");
+    }
+
+    #[test]
+    fn placemark() {
+        let output = tempfile::tempdir().unwrap();
+        let mut writer =
+            KmlWriter::create(output.path(), "taxi", KmlFormat::Kml, Compression::None).unwrap();
+        writer.style("taxi", COLORS[0]).unwrap();
+        writer.open_folder("Taxi & co").unwrap();
+        writer
+            .write(
+                &json!({"address": "Via <S.> Croce", "totalSlots": 4, "zone": null}),
+                "taxi",
+                &location(),
+            )
+            .unwrap();
+        writer.close_folder().unwrap();
+        writer.finish().unwrap();
+
+        let content = fs::read_to_string(output.path().join("taxi.kml")).unwrap();
+        assert!(content.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(content.contains("<Folder><name>Taxi &amp; co</name><Placemark>"));
+        assert!(content.contains(
+            "<name>Bar &quot;Dante&quot; &amp; &lt;Caffè&gt;</name><styleUrl>#taxi</styleUrl>"
+        ));
+        assert!(content.contains(
+            r#"<Data name="address"><value>Via &lt;S.&gt; Croce</value></Data><Data name="totalSlots"><value>4</value></Data></ExtendedData>"#
+        ));
+        assert!(!content.contains("zone"));
+        assert!(content.contains("<coordinates>11.12,46.07</coordinates>"));
+        assert!(content.ends_with("</Folder></Document></kml>"));
+    }
+
+    #[test]
+    fn kmz() {
+        let output = tempfile::tempdir().unwrap();
+        let mut writer =
+            KmlWriter::create(output.path(), "mobility", KmlFormat::Kmz, Compression::Gzip)
+                .unwrap();
+        writer
+            .write(&json!({"type": "Taxi"}), "taxi", &location())
+            .unwrap();
+        writer.finish().unwrap();
+
+        let files: Vec<_> = fs::read_dir(output.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, ["mobility.kmz"]);
+        let file = File::open(output.path().join("mobility.kmz")).unwrap();
+        let mut archive = ZipArchive::new(file).unwrap();
+        assert_eq!(archive.len(), 1);
+        let mut content = String::new();
+        archive
+            .by_name("doc.kml")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert!(content.contains("<Document><name>mobility</name><Placemark>"));
+        assert!(content.ends_with("</Placemark></Document></kml>"));
+    }
+}
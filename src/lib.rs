@@ -0,0 +1,49 @@
+//! # KDI alignment | Trentino Transportation
+//!
+//! Alignment of the Trentino transportation datasets (`GTFS` feeds, fare archives,
+//! `KML` layers and bike sharing `JSON` files) into the `Kdi*` entity schema.
+//!
+//! ```no_run
+//! use kdi::{Alignment, Config, Group, Sources};
+//! use std::path::Path;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = Config::from_file(Path::new("./kdi.toml"))?;
+//! let sources = Sources::read(&config)?;
+//! let alignment = Alignment::align(&sources, &[Group::Common, Group::Core], false)?;
+//! println!("{} locations", alignment.locations.len());
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod align;
+pub mod alignment;
+pub mod config;
+pub mod enums;
+pub mod error;
+pub mod geography;
+pub mod geojson;
+pub mod gtfs;
+pub mod integrity;
+pub mod json;
+pub mod kml;
+pub mod neo4j;
+pub mod ontology;
+pub mod output;
+pub mod parquet;
+pub mod rdf;
+pub mod rejection;
+pub mod schema;
+pub mod service;
+pub mod source;
+pub mod sqlite;
+pub mod stop_times;
+pub mod structs;
+
+pub use align::TT;
+pub use alignment::{Alignment, Group};
+pub use config::Config;
+pub use error::KdiError;
+pub use output::OutputOptions;
+pub use rdf::RdfOptions;
+pub use source::Sources;
@@ -1,47 +1,21 @@
-mod kdi;
+mod cli;
 
+use clap::Parser;
 use env_logger::{Builder, Target};
-use gtfs_structures::Gtfs;
-use kdi::enums::KdiFareEnum;
-use log::{debug, info, LevelFilter};
-use serde_json::json;
-use serde_xml_rs::{self};
-use std::collections::{HashMap, HashSet};
+use kdi::kml::KmlFormat;
+use kdi::rdf::RdfFormat;
+use kdi::{output, Alignment, Config, Group, KdiError, RdfOptions, Sources};
+use log::{error, info, LevelFilter};
 use std::error::Error;
-use std::fs::{self, File};
-use strum::VariantNames;
-use zip::ZipArchive;
+use std::fs;
+use std::io;
+use std::process;
 
-use crate::kdi::align::{self, TT};
-use crate::kdi::enums::{
-    KdiCurrencyEnum, KdiDirectionEnum, KdiExceptionEnum, KdiParkingStopEnum, KdiPaymentEnum,
-    KdiSupportedEnum, KdiTransportEnum,
-};
-use crate::kdi::json::BikeSharing;
-use crate::kdi::kml::Kml;
-use crate::kdi::structs::{
-    KdiAgency, KdiBikeSharingStop, KdiCalendar, KdiCalendarException, KdiFare, KdiFareRule,
-    KdiLocation, KdiParkingStop, KdiPublicTransportStop, KdiRoute, KdiStopTime, KdiTrip,
-};
+use crate::cli::{AlignArgs, Cli, Command, Format, OntologyArgs, SchemaArgs, ValidateArgs};
 
-const ALIGNEMENT_DIR: &str = "./alignment";
-const EXTRAURBAN_FILE: &str = "./data/extraurban.zip";
-const URBAN_FILE: &str = "./data/urban.zip";
-const EXTRAURBAN_FARE_FILE: &str = "./data/extraurban_fare.zip";
-const URBAN_FARE_FILE: &str = "./data/urban_fare.zip";
-const CAR_SHARING_FILE: &str = "./data/car_sharing.kml";
-const CENTRO_IN_BICI_FILE: &str = "./data/centro_in_bici.kml";
-const PARCHEGGIO_PROTETTO_BICICLETTE: &str = "./data/parcheggio_protetto_biciclette.kml";
-const TAXI_FILE: &str = "./data/taxi.kml";
-const BIKESHARING_LAVIS: &str = "./data/bikesharing_lavis.json";
-const BIKESHARING_MEZZOCORONA: &str = "./data/bikesharing_mezzocorona.json";
-const BIKESHARING_MEZZOLOMBARDO: &str = "./data/bikesharing_mezzolombardo.json";
-const BIKESHARING_ROVERETO: &str = "./data/bikesharing_rovereto.json";
-const BIKESHARING_SAN_MICHELE_ALLADIGE: &str = "./data/bikesharing_sanmichelealladige.json";
-const BIKESHARING_TRENTO: &str = "./data/bikesharing_trento.json";
-const FARE_RULES_CHUNK_SIZE: usize = 100_000;
+fn main() {
+    let cli = Cli::parse();
 
-fn main() -> Result<(), Box<dyn Error>> {
     // --- LOGGER
     // - Initialize logger
     Builder::new()
@@ -50,348 +24,141 @@ fn main() -> Result<(), Box<dyn Error>> {
         .filter_module("serde_xml_rs::de", LevelFilter::Off)
         .init();
 
+    let result = match cli.command {
+        Command::Align(args) => align(&args),
+        Command::Ontology(args) => ontology(&args),
+        Command::Schema(args) => schema(&args),
+        Command::Validate(args) => validate(&args),
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn align(args: &AlignArgs) -> Result<(), Box<dyn Error>> {
+    // --- CONFIGURATION
+    let config = match &args.config {
+        Some(path) => {
+            info!("Reading configuration `{}`", path.display());
+            let mut config = Config::from_file(path)?;
+            if let Some(area) = &args.bbox {
+                config.area = area.clone();
+            }
+            config
+        }
+        None => args.to_config(),
+    };
+    let groups: Vec<Group> = args.groups.iter().map(|&group| group.into()).collect();
+
     // --- DIRECTORY TREE
-    // - Remove and recreate alignment directory
-    info!("Removing `{}` directory", ALIGNEMENT_DIR);
-    fs::remove_dir_all(ALIGNEMENT_DIR).ok();
-    info!("Creating `{}` directory", ALIGNEMENT_DIR);
-    fs::create_dir(ALIGNEMENT_DIR)?;
+    // - Replace a non-empty alignment directory only when forced
+    let io_error = |source| KdiError::Io {
+        file: args.output.clone(),
+        source,
+    };
+    let is_empty = match fs::read_dir(&args.output) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => true,
+        Err(error) => return Err(io_error(error).into()),
+    };
+    if !is_empty {
+        if !args.force {
+            return Err(KdiError::OutputNotEmpty {
+                dir: args.output.clone(),
+            }
+            .into());
+        }
+        info!("Removing `{}` directory", args.output.display());
+        fs::remove_dir_all(&args.output).map_err(io_error)?;
+    }
+    info!("Creating `{}` directory", args.output.display());
+    fs::create_dir_all(&args.output).map_err(io_error)?;
 
     // --- DATA FILES
-    // - Read `GTFS` files
-    info!("Reading `{}`", EXTRAURBAN_FILE);
-    let gtfs_extraurban = Gtfs::new(EXTRAURBAN_FILE)?;
-    info!("Reading `{}`", URBAN_FILE);
-    let gtfs_urban = Gtfs::new(URBAN_FILE)?;
-    // - Read `FARE` files
-    info!("Reading `{}`", EXTRAURBAN_FARE_FILE);
-    let mut extraurban_fare = ZipArchive::new(File::open(&EXTRAURBAN_FARE_FILE)?)?;
-    info!("Reading `{}`", URBAN_FARE_FILE);
-    let mut urban_fare = ZipArchive::new(File::open(&URBAN_FARE_FILE)?)?;
-    // - Read `KML` files
-    info!("Reading `{}`", CAR_SHARING_FILE);
-    let car_sharing: Kml = serde_xml_rs::from_str(&fs::read_to_string(CAR_SHARING_FILE)?)?;
-    info!("Reading `{}`", CENTRO_IN_BICI_FILE);
-    let centro_in_bici: Kml = serde_xml_rs::from_str(&fs::read_to_string(CENTRO_IN_BICI_FILE)?)?;
-    info!("Reading `{}`", PARCHEGGIO_PROTETTO_BICICLETTE);
-    let parcheggio_protetto_biciclette: Kml =
-        serde_xml_rs::from_str(&fs::read_to_string(PARCHEGGIO_PROTETTO_BICICLETTE)?)?;
-    info!("Reading `{}`", TAXI_FILE);
-    let taxi: Kml = serde_xml_rs::from_str(&fs::read_to_string(TAXI_FILE)?)?;
-    // - Read `BIKESHARING` files
-    let mut bike_sharing: Vec<BikeSharing> = Vec::new();
-    info!("Reading `{}`", BIKESHARING_LAVIS);
-    bike_sharing.append(&mut serde_json::from_str(&fs::read_to_string(
-        BIKESHARING_LAVIS,
-    )?)?);
-    info!("Reading `{}`", BIKESHARING_MEZZOCORONA);
-    bike_sharing.append(&mut serde_json::from_str(&fs::read_to_string(
-        BIKESHARING_MEZZOCORONA,
-    )?)?);
-    info!("Reading `{}`", BIKESHARING_MEZZOLOMBARDO);
-    bike_sharing.append(&mut serde_json::from_str(&fs::read_to_string(
-        BIKESHARING_MEZZOLOMBARDO,
-    )?)?);
-    info!("Reading `{}`", BIKESHARING_ROVERETO);
-    bike_sharing.append(&mut serde_json::from_str(&fs::read_to_string(
-        BIKESHARING_ROVERETO,
-    )?)?);
-    info!("Reading `{}`", BIKESHARING_SAN_MICHELE_ALLADIGE);
-    bike_sharing.append(&mut serde_json::from_str(&fs::read_to_string(
-        BIKESHARING_SAN_MICHELE_ALLADIGE,
-    )?)?);
-    info!("Reading `{}`", BIKESHARING_TRENTO);
-    bike_sharing.append(&mut serde_json::from_str(&fs::read_to_string(
-        BIKESHARING_TRENTO,
-    )?)?);
-    let bike_sharing = bike_sharing;
+    let sources = if groups.iter().any(Group::requires_sources) {
+        Sources::read(&config)?
+    } else {
+        Sources::default()
+    };
 
-    // --- COMMON
-    // - Location
-    info!("Aligning `Common::Location`");
-    let mut locations: Vec<KdiLocation> = Vec::new();
-    // Zone
-    debug!("Aligning extraurban `Common::Location::Zone`");
-    align::align_location_zone(&mut extraurban_fare, &mut locations, TT::ExtraUrban)?;
-    debug!("Aligning urban `Common::Location::Zone`");
-    align::align_location_zone(&mut urban_fare, &mut locations, TT::Urban)?;
-    // PublicTransportStop
-    debug!("Aligning extraurban `Common::Location::PublicTransportStop`");
-    align::align_location_public_transport_stop(&gtfs_extraurban, &mut locations, TT::ExtraUrban)?;
-    debug!("Aligning urban `Common::Location::PublicTransportStop`");
-    align::align_location_public_transport_stop(&gtfs_urban, &mut locations, TT::Urban)?;
-    // CarSharing
-    debug!("Aligning `Common::Location::CarSharing`");
-    align::align_location_car_sharing(&car_sharing, &mut locations)?;
-    // CentroInBici
-    debug!("Aligning `Common::Location::CentroInBici`");
-    align::align_location_centro_in_bici(&centro_in_bici, &mut locations)?;
-    // ParcheggioProtettoBiciclette
-    debug!("Aligning `Common::Location::ParcheggioProtettoBiciclette`");
-    align::align_location_parcheggio_protetto_biciclette(
-        &parcheggio_protetto_biciclette,
-        &mut locations,
-    )?;
-    // Taxi
-    debug!("Aligning `Common::Location::Taxi`");
-    align::align_location_taxi(&taxi, &mut locations)?;
-    // BikeSharing
-    debug!("Aligning `Common::Location::Bikesharing`");
-    align::align_location_bike_sharing(&bike_sharing, &mut locations)?;
-    info!("Writing `locations.json` file");
-    fs::write(
-        format!("{}/locations.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&locations)?,
-    )?;
-    // - CalendarException
-    let mut calendar_exceptions: Vec<KdiCalendarException> = Vec::new();
-    info!("Aligning `Common::CalendarException`");
-    debug!("Aligning extraurban `Common::CalendarException`");
-    align::align_calendar_exception(&gtfs_extraurban, &mut calendar_exceptions, TT::ExtraUrban)?;
-    debug!("Aligning urban `Common::CalendarException`");
-    align::align_calendar_exception(&gtfs_urban, &mut calendar_exceptions, TT::Urban)?;
-    info!("Writing `calendar_exceptions.json` file");
-    fs::write(
-        format!("{}/calendar_exceptions.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&calendar_exceptions)?,
-    )?;
-    // - Calendar
-    let mut calendars: Vec<KdiCalendar> = Vec::new();
-    info!("Aligning `Common::Calendar`");
-    debug!("Aligning extraurban `Common::Calendar`");
-    align::align_calendar(&gtfs_extraurban, &mut calendars, TT::ExtraUrban)?;
-    debug!("Aligning urban `Common::Calendar`");
-    align::align_calendar(&gtfs_urban, &mut calendars, TT::Urban)?;
-    info!("Writing `calendars.json` file");
-    fs::write(
-        format!("{}/calendars.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&calendars)?,
-    )?;
-    // - Agency
-    let mut agencies: Vec<KdiAgency> = Vec::new();
-    info!("Aligning `Common::Agency`");
-    assert!(gtfs_extraurban.agencies.len() == 1);
-    assert!(gtfs_urban.agencies.len() == 1);
-    {
-        let gtfs_agency = gtfs_extraurban.agencies.first().unwrap();
-        agencies.push(KdiAgency {
-            id: gtfs_agency.id.as_ref().unwrap(),
-            name: &gtfs_agency.name,
-            email: "info@trentinotrasporti.it",
-            phone: gtfs_agency.phone.as_ref().unwrap(),
-            url: &gtfs_agency.url,
-        });
+    // --- ALIGNMENT
+    let alignment = Alignment::align(&sources, &groups, args.lenient)?;
+    if args.check {
+        // All checks report their findings before the first failure is returned
+        let results = [
+            alignment.check_integrity(),
+            alignment.check_stop_times(),
+            alignment.check_calendars(),
+        ];
+        for result in results {
+            result?;
+        }
     }
-    info!("Writing `agencies.json` file");
-    fs::write(
-        format!("{}/agencies.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&agencies)?,
-    )?;
-
-    // --- CORE
-    // - FareRule
-    info!("Aligning `Core:FareRule`");
-    let mut fare_rules: Vec<KdiFareRule> = Vec::new();
-    debug!("Aligning extraurban `Core:FareRule`");
-    align::align_fare_rule(&mut extraurban_fare, &mut fare_rules, TT::ExtraUrban)?;
-    debug!("Aligning urban `Core:FareRule`");
-    align::align_fare_rule(&mut urban_fare, &mut fare_rules, TT::Urban)?;
-    info!("Writing `fare_rules.json` file");
-    fs::write(
-        format!("{}/fare_rules.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&fare_rules)?,
-    )?;
-    info!("Writing `fare_rules.json` chuncks file");
-    for (chunk_index, chunk) in fare_rules.chunks(FARE_RULES_CHUNK_SIZE).enumerate() {
-        debug!("Writing `fare_rules_{}.json` file", chunk_index);
-        fs::write(
-            format!("{}/fare_rules_{}.json", ALIGNEMENT_DIR, chunk_index),
-            serde_json::to_string(&chunk)?,
-        )?;
+    match args.format {
+        Format::Json => {
+            alignment.write(&args.output, &args.to_output_options(output::Format::Json))?
+        }
+        Format::Jsonl => {
+            alignment.write(&args.output, &args.to_output_options(output::Format::Jsonl))?
+        }
+        Format::Turtle => {
+            alignment.write_rdf(&args.output, &args.to_rdf_options(RdfFormat::Turtle))?
+        }
+        Format::NTriples => {
+            alignment.write_rdf(&args.output, &args.to_rdf_options(RdfFormat::NTriples))?
+        }
+        Format::JsonLd => {
+            alignment.write_rdf(&args.output, &args.to_rdf_options(RdfFormat::JsonLd))?
+        }
+        Format::GeoJson => alignment.write_geojson(&args.output, args.compression.into())?,
+        Format::Gtfs => alignment.write_gtfs(&args.output)?,
+        Format::Sqlite => alignment.write_sqlite(&args.output)?,
+        Format::Neo4j => alignment.write_neo4j(&args.output, args.compression.into())?,
+        Format::Parquet => alignment.write_parquet(&args.output, args.compression.into())?,
+        Format::Kml => {
+            alignment.write_kml(&args.output, KmlFormat::Kml, args.compression.into())?
+        }
+        Format::Kmz => {
+            alignment.write_kml(&args.output, KmlFormat::Kmz, args.compression.into())?
+        }
+    }
+    if args.service_dates {
+        let format = match args.format {
+            Format::Jsonl => output::Format::Jsonl,
+            _ => output::Format::Json,
+        };
+        alignment.write_service_dates(&args.output, &args.to_output_options(format))?;
     }
 
-    // - ParkingStop
-    info!("Aligning `Core::ParkingStop`");
-    let mut parking_stops: Vec<KdiParkingStop> = Vec::new();
-    // CarSharing
-    debug!("Aligning `Core::ParkingStop::CarSharing`");
-    align::align_parking_stop_car_sharing(&car_sharing, &mut parking_stops)?;
-    // CentroInBici
-    debug!("Aligning `Core::ParkingStop::CentroInBici`");
-    align::align_parking_stop_centro_in_bici(&centro_in_bici, &mut parking_stops)?;
-    // ParcheggioProtettoBiciclette
-    debug!("Aligning `Core::ParkingStop::ParcheggioProtettoBiciclette`");
-    align::align_parking_stop_parcheggio_protetto_biciclette(
-        &parcheggio_protetto_biciclette,
-        &mut parking_stops,
-    )?;
-    // Taxi
-    debug!("Aligning `Core::ParkingStop::Taxi`");
-    align::align_parking_stop_taxi(&taxi, &mut parking_stops)?;
-    info!("Writing `parking_stops.json` file");
-    fs::write(
-        format!("{}/parking_stops.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&parking_stops)?,
-    )?;
-    // - Fare
-    let mut fares: Vec<KdiFare> = Vec::new();
-    info!("Aligning `Core::Fare`");
-    debug!("Aligning extraurban `Core::Fare`");
-    align::align_fare(&mut extraurban_fare, &mut fares, TT::ExtraUrban)?;
-    debug!("Aligning urban `Core::Fare`");
-    align::align_fare(&mut urban_fare, &mut fares, TT::Urban)?;
-    info!("Writing `fares.json` file");
-    fs::write(
-        format!("{}/fares.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&fares)?,
-    )?;
-    // - BikeSharingStop
-    let mut bike_sharing_stops: Vec<KdiBikeSharingStop> = Vec::new();
-    info!("Aligning `Core::BikeSharingStop`");
-    align::align_bike_sharing_stop(&bike_sharing, &mut bike_sharing_stops)?;
-    info!("Writing `bike_sharing_stops.json` file");
-    fs::write(
-        format!("{}/bike_sharing_stops.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&bike_sharing_stops)?,
-    )?;
+    Ok(())
+}
 
-    // - StopTime
-    let mut stop_times: Vec<KdiStopTime> = Vec::new();
-    info!("Aligning `Core::StopTime`");
-    debug!("Aligning extraurban `Core::StopTime`");
-    align::align_stop_time(&gtfs_extraurban, &mut stop_times, TT::ExtraUrban)?;
-    debug!("Aligning urban `Core::StopTime`");
-    align::align_stop_time(&gtfs_urban, &mut stop_times, TT::Urban)?;
-    info!("Writing `stop_times.json` file");
-    fs::write(
-        format!("{}/stop_times.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&stop_times)?,
-    )?;
-    // - Trip
-    let mut trips: Vec<KdiTrip> = Vec::new();
-    info!("Aligning `Common::Trip`");
-    debug!("Aligning extraurban `Common::Trip`");
-    align::align_trip(&gtfs_extraurban, &mut trips, TT::ExtraUrban)?;
-    debug!("Aligning urban `Common::Trip`");
-    align::align_trip(&gtfs_urban, &mut trips, TT::Urban)?;
-    info!("Writing `trips.json` file");
-    fs::write(
-        format!("{}/trips.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&trips)?,
-    )?;
-    // - Route
-    let mut routes: Vec<KdiRoute> = Vec::new();
-    info!("Aligning `Common::Route`");
-    debug!("Aligning extraurban `Common::Route`");
-    align::align_route(&gtfs_extraurban, &mut routes, TT::ExtraUrban)?;
-    debug!("Aligning urban `Common::Route`");
-    align::align_route(&gtfs_urban, &mut routes, TT::Urban)?;
-    info!("Writing `routes.json` file");
-    fs::write(
-        format!("{}/routes.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&routes)?,
-    )?;
-    // - PublicTransportStop
-    let mut public_transport_stops: Vec<KdiPublicTransportStop> = Vec::new();
-    info!("Aligning `Core::PublicTransportStop`");
-    debug!("Aligning extraurban `Core::PublicTransportStop`");
-    align::align_public_transport_stop(
-        &gtfs_extraurban,
-        &mut public_transport_stops,
-        TT::ExtraUrban,
-    )?;
-    debug!("Aligning urban `Core::PublicTransportStop`");
-    align::align_public_transport_stop(&gtfs_urban, &mut public_transport_stops, TT::Urban)?;
-    // -_-
-    let mut transport_mapping: HashMap<&String, HashSet<KdiTransportEnum>> = HashMap::new();
-    for stop in public_transport_stops.iter_mut() {
-        let stop_times_filtered: Vec<&KdiStopTime> = stop_times
-            .iter()
-            .filter(|st| st.stop == stop.location)
-            .collect();
-        let trips_filtered: Vec<&KdiTrip> = trips
-            .iter()
-            .filter(|t| stop_times_filtered.iter().any(|&st| st.trip == t.id))
-            .collect();
-        let routes_filtered: Vec<&KdiRoute> = routes
-            .iter()
-            .filter(|r| trips_filtered.iter().any(|&t| t.route == r.id))
-            .collect();
+fn ontology(args: &OntologyArgs) -> Result<(), Box<dyn Error>> {
+    let options = RdfOptions {
+        base_iri: args.base_iri.clone(),
+        ..RdfOptions::default()
+    };
+    info!("Creating `{}` directory", args.output.display());
+    fs::create_dir_all(&args.output)?;
+    kdi::ontology::write(&args.output, &options)?;
 
-        for route in &routes_filtered {
-            if !transport_mapping.contains_key(&stop.location) {
-                transport_mapping.insert(&stop.location, HashSet::new());
-            }
-            transport_mapping
-                .get_mut(&stop.location)
-                .unwrap()
-                .insert(route.transport.clone());
-        }
+    Ok(())
+}
 
-        stop.ptype = Vec::from_iter(
-            transport_mapping
-                .get(&stop.location)
-                .unwrap()
-                .iter()
-                .cloned(),
-        );
-    }
-    info!("Writing `public_transport_stops.json` file");
-    fs::write(
-        format!("{}/public_transport_stops.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&public_transport_stops)?,
-    )?;
+fn schema(args: &SchemaArgs) -> Result<(), Box<dyn Error>> {
+    info!("Creating `{}` directory", args.output.display());
+    fs::create_dir_all(&args.output)?;
+    kdi::schema::write(&args.output)?;
+
+    Ok(())
+}
 
-    // --- CONTEXTUAL
-    info!("Aligning `Contextual::*`");
-    // - PaymentEnum
-    info!("Writing `payment_enum.json` file");
-    fs::write(
-        format!("{}/payment_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiPaymentEnum::VARIANTS }))?,
-    )?;
-    // - ParkingStopEnum
-    info!("Writing `parking_stop_enum.json` file");
-    fs::write(
-        format!("{}/parking_stop_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiParkingStopEnum::VARIANTS }))?,
-    )?;
-    // - CurrencyEnum
-    info!("Writing `currency_enum.json` file");
-    fs::write(
-        format!("{}/currency_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiCurrencyEnum::VARIANTS }))?,
-    )?;
-    // - FareEnum
-    info!("Writing `fare_enum.json` file");
-    fs::write(
-        format!("{}/fare_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiFareEnum::VARIANTS }))?,
-    )?;
-    // - SupportedEnum
-    info!("Writing `supported_enum.json` file");
-    fs::write(
-        format!("{}/supported_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiSupportedEnum::VARIANTS }))?,
-    )?;
-    // - DirectionEnum
-    info!("Writing `direction_enum.json` file");
-    fs::write(
-        format!("{}/direction_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiDirectionEnum::VARIANTS }))?,
-    )?;
-    // - ExceptionEnum
-    info!("Writing `exception_enum.json` file");
-    fs::write(
-        format!("{}/exception_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiExceptionEnum::VARIANTS }))?,
-    )?;
-    // - TransportEnum
-    info!("Writing `transport_enum.json` file");
-    fs::write(
-        format!("{}/transport_enum.json", ALIGNEMENT_DIR),
-        serde_json::to_string(&json!({ "value": KdiTransportEnum::VARIANTS }))?,
-    )?;
+fn validate(args: &ValidateArgs) -> Result<(), Box<dyn Error>> {
+    // Both checks report their findings before failing
+    let schema = kdi::schema::validate(&args.input);
+    kdi::integrity::check(&args.input)?;
+    schema?;
 
     Ok(())
 }
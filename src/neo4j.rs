@@ -0,0 +1,208 @@
+use log::{info, warn};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::KdiError;
+use crate::output::{Compression, OutputFile};
+
+/// `neo4j-admin import` `CSV` file, of nodes or of relationships.
+/// Every row ends with the label of the node or the type of the relationship.
+/// `neo4j-admin` reads plain and gzip files only, see [`ImportFile::compression`]
+pub struct ImportFile {
+    path: PathBuf,
+    writer: csv::Writer<OutputFile>,
+    kind: &'static str,
+}
+
+impl ImportFile {
+    /// `nodes_<name>` file of the `label` nodes, in the `label` ID space.
+    /// `properties` are the header fields of the properties, e.g. `latitude:double`
+    pub fn nodes(
+        output: &Path,
+        name: &str,
+        compression: Compression,
+        label: &'static str,
+        properties: &[&str],
+    ) -> Result<ImportFile, KdiError> {
+        let id = format!("id:ID({})", label);
+        let mut header = vec![id.as_str()];
+        header.extend(properties);
+        header.push(":LABEL");
+        ImportFile::create(
+            output,
+            &format!("nodes_{}", name),
+            compression,
+            label,
+            &header,
+        )
+    }
+
+    /// `relationships_<name>` file of the `kind` relationships,
+    /// from the `start` ID space to the `end` ID space
+    pub fn relationships(
+        output: &Path,
+        name: &str,
+        compression: Compression,
+        kind: &'static str,
+        (start, end): (&str, &str),
+        properties: &[&str],
+    ) -> Result<ImportFile, KdiError> {
+        let start = format!(":START_ID({})", start);
+        let end = format!(":END_ID({})", end);
+        let mut header = vec![start.as_str(), end.as_str()];
+        header.extend(properties);
+        header.push(":TYPE");
+        ImportFile::create(
+            output,
+            &format!("relationships_{}", name),
+            compression,
+            kind,
+            &header,
+        )
+    }
+
+    /// `compression` if `neo4j-admin` reads it, no compression otherwise
+    pub fn compression(compression: Compression) -> Compression {
+        match compression {
+            Compression::Zstd => {
+                warn!("`neo4j-admin` does not read zstd files, writing uncompressed import files");
+                Compression::None
+            }
+            compression => compression,
+        }
+    }
+
+    fn create(
+        output: &Path,
+        name: &str,
+        compression: Compression,
+        kind: &'static str,
+        header: &[&str],
+    ) -> Result<ImportFile, KdiError> {
+        let file_name = compression.file_name(&format!("{}.csv", name));
+        info!("Writing `{}` file", file_name);
+        let path = output.join(file_name);
+        if let Compression::Zstd = compression {
+            return Err(KdiError::UnsupportedCompression {
+                file: path,
+                extension: "zst",
+            });
+        }
+        let mut file = ImportFile {
+            writer: csv::Writer::from_writer(OutputFile::create(&path, compression)?),
+            path,
+            kind,
+        };
+        file.write_record(header)?;
+
+        Ok(file)
+    }
+
+    /// Write the node `id`, or the relationship `start` and `end` IDs, followed by the properties
+    pub fn write(&mut self, fields: &[&str]) -> Result<(), KdiError> {
+        let mut record = fields.to_vec();
+        record.push(self.kind);
+        self.write_record(&record)
+    }
+
+    fn write_record(&mut self, record: &[&str]) -> Result<(), KdiError> {
+        self.writer
+            .write_record(record)
+            .map_err(|source| KdiError::Csv {
+                file: self.path.clone(),
+                source,
+            })
+    }
+
+    pub fn finish(self) -> Result<(), KdiError> {
+        let path = self.path;
+        match self.writer.into_inner() {
+            Ok(file) => file.finish(),
+            Err(error) => Err(KdiError::Io {
+                file: path,
+                source: io::Error::new(error.error().kind(), error.error().to_string()),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::fs::{self, File};
+    use std::io::Read;
+
+    #[test]
+    fn nodes_layout() {
+        let output = tempfile::tempdir().unwrap();
+        let mut file = ImportFile::nodes(
+            output.path(),
+            "locations",
+            Compression::None,
+            "Location",
+            &["name", "latitude:double"],
+        )
+        .unwrap();
+        file.write(&["U_1", "Piazza Dante, Trento", "46.07"])
+            .unwrap();
+        file.finish().unwrap();
+
+        let content = fs::read_to_string(output.path().join("nodes_locations.csv")).unwrap();
+        assert_eq!(
+            content,
+            "id:ID(Location),name,latitude:double,:LABEL\n\
+             U_1,\"Piazza Dante, Trento\",46.07,Location\n"
+        );
+    }
+
+    #[test]
+    fn relationships_layout() {
+        let output = tempfile::tempdir().unwrap();
+        let mut file = ImportFile::relationships(
+            output.path(),
+            "applies_between",
+            Compression::Gzip,
+            "APPLIES_BETWEEN",
+            ("Zone", "Zone"),
+            &["fare", "route"],
+        )
+        .unwrap();
+        file.write(&["ZONE_EU_1", "ZONE_EU_2", "EU_4KM", ""])
+            .unwrap();
+        file.finish().unwrap();
+
+        let path = output.path().join("relationships_applies_between.csv.gz");
+        let mut content = String::new();
+        GzDecoder::new(File::open(path).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(
+            content,
+            ":START_ID(Zone),:END_ID(Zone),fare,route,:TYPE\n\
+             ZONE_EU_1,ZONE_EU_2,EU_4KM,,APPLIES_BETWEEN\n"
+        );
+    }
+
+    #[test]
+    fn zstd_rejected() {
+        let output = tempfile::tempdir().unwrap();
+        let file = ImportFile::nodes(output.path(), "zones", Compression::Zstd, "Zone", &[]);
+        assert!(matches!(
+            file,
+            Err(KdiError::UnsupportedCompression {
+                extension: "zst",
+                ..
+            })
+        ));
+        assert_eq!(fs::read_dir(output.path()).unwrap().count(), 0);
+        assert_eq!(
+            ImportFile::compression(Compression::Zstd),
+            Compression::None
+        );
+        assert_eq!(
+            ImportFile::compression(Compression::Gzip),
+            Compression::Gzip
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use log::info;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::error::KdiError;
+use crate::rdf::{self, Enumeration, Property, Range, RdfOptions};
+
+const PREFIXES: &str = "@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .
+@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+@prefix owl: <http://www.w3.org/2002/07/owl#> .
+@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+@prefix sh: <http://www.w3.org/ns/shacl#> .
+";
+
+/// Write the `OWL` ontology (`ontology.ttl`) and the `SHACL` shapes (`shapes.ttl`)
+/// of the entity schema under `output`
+pub fn write(output: &Path, options: &RdfOptions) -> Result<(), KdiError> {
+    for (name, content) in [
+        ("ontology.ttl", ontology(options)),
+        ("shapes.ttl", shapes(options)),
+    ] {
+        let path = output.join(name);
+        info!("Writing `{}` file", path.display());
+        fs::write(&path, content).map_err(|source| KdiError::Io { file: path, source })?;
+    }
+
+    Ok(())
+}
+
+/// `OWL` ontology, in `Turtle`.
+/// Every class, enumeration and property of the schema with its domain and range
+pub fn ontology(options: &RdfOptions) -> String {
+    let mut ttl = header(options);
+    let ontology = options.ontology_iri();
+    let _ = writeln!(
+        ttl,
+        "<{}> a owl:Ontology ;\n    rdfs:label \"KDI Trentino transportation ontology\" .\n",
+        ontology
+    );
+
+    // --- CLASSES
+    for (class, _) in rdf::classes() {
+        let _ = writeln!(
+            ttl,
+            "kdi:{} a owl:Class ;\n    rdfs:label \"{}\" .",
+            class, class
+        );
+    }
+    ttl.push('\n');
+
+    // --- ENUMERATIONS
+    for enumeration in rdf::ENUMERATIONS {
+        let _ = write!(
+            ttl,
+            "kdi:{} a owl:Class ;\n    rdfs:label \"{}\"",
+            enumeration.class, enumeration.class
+        );
+        if !enumeration.open {
+            let _ = write!(
+                ttl,
+                " ;\n    owl:oneOf {}",
+                list(&individuals(options, &enumeration))
+            );
+        }
+        ttl.push_str(" .\n");
+        for individual in individuals(options, &enumeration) {
+            let _ = writeln!(
+                ttl,
+                "{} a owl:NamedIndividual, kdi:{} .",
+                individual, enumeration.class
+            );
+        }
+        ttl.push('\n');
+    }
+
+    // --- PROPERTIES
+    for (name, uses) in properties() {
+        let object = uses
+            .iter()
+            .any(|(_, property)| matches!(property.range, Range::Class(_)));
+        let functional = uses.iter().all(|(_, property)| !property.multiple);
+        let mut domains: Vec<String> = uses.iter().map(|(class, _)| kdi(class)).collect();
+        domains.dedup();
+        let mut ranges: Vec<String> = uses
+            .iter()
+            .map(|(_, property)| range(&property.range))
+            .collect();
+        ranges.sort();
+        ranges.dedup();
+
+        let _ = write!(
+            ttl,
+            "kdi:{} a {}",
+            name,
+            if object {
+                "owl:ObjectProperty"
+            } else {
+                "owl:DatatypeProperty"
+            }
+        );
+        if functional {
+            ttl.push_str(", owl:FunctionalProperty");
+        }
+        let _ = write!(
+            ttl,
+            " ;\n    rdfs:label \"{}\" ;\n    rdfs:domain {} ;\n    rdfs:range {} .\n",
+            name,
+            union(&domains),
+            union(&ranges)
+        );
+    }
+
+    ttl
+}
+
+/// `SHACL` shapes, in `Turtle`.
+/// One node shape per class, with the datatype or class, the cardinality
+/// and, for closed enumerations, the allowed individuals of every property
+pub fn shapes(options: &RdfOptions) -> String {
+    let mut ttl = header(options);
+    ttl.pop();
+    let _ = writeln!(ttl, "@prefix kdish: <{}shapes/> .\n", options.base_iri);
+
+    for (class, properties) in rdf::classes() {
+        let _ = write!(
+            ttl,
+            "kdish:{} a sh:NodeShape ;\n    sh:targetClass kdi:{}",
+            class, class
+        );
+        for property in properties {
+            let _ = write!(
+                ttl,
+                " ;\n    sh:property [\n        sh:path kdi:{}",
+                property.name
+            );
+            match property.range {
+                Range::Literal(datatype) => {
+                    let _ = write!(ttl, " ;\n        sh:datatype xsd:{}", datatype.xsd());
+                }
+                Range::Class(class) => {
+                    let enumeration = rdf::ENUMERATIONS
+                        .iter()
+                        .find(|enumeration| enumeration.class == class);
+                    match enumeration {
+                        // Values outside of the variants are not declared individuals
+                        Some(enumeration) if enumeration.open => {
+                            ttl.push_str(" ;\n        sh:nodeKind sh:IRI");
+                        }
+                        Some(enumeration) => {
+                            let _ = write!(
+                                ttl,
+                                " ;\n        sh:class kdi:{} ;\n        sh:in {}",
+                                class,
+                                list(&individuals(options, enumeration))
+                            );
+                        }
+                        None => {
+                            let _ = write!(ttl, " ;\n        sh:class kdi:{}", class);
+                        }
+                    }
+                }
+            }
+            if !property.optional {
+                ttl.push_str(" ;\n        sh:minCount 1");
+            }
+            if !property.multiple {
+                ttl.push_str(" ;\n        sh:maxCount 1");
+            }
+            ttl.push_str("\n    ]");
+        }
+        ttl.push_str(" .\n\n");
+    }
+
+    ttl
+}
+
+fn header(options: &RdfOptions) -> String {
+    format!(
+        "{}@prefix kdi: <{}> .\n\n",
+        PREFIXES,
+        options.ontology_iri()
+    )
+}
+
+/// Properties by name, with every class using them
+fn properties() -> BTreeMap<&'static str, Vec<(&'static str, &'static Property)>> {
+    let mut properties: BTreeMap<&str, Vec<(&str, &Property)>> = BTreeMap::new();
+    for (class, class_properties) in rdf::classes() {
+        for property in class_properties {
+            properties
+                .entry(property.name)
+                .or_default()
+                .push((class, property));
+        }
+    }
+    properties
+}
+
+fn individuals(options: &RdfOptions, enumeration: &Enumeration) -> Vec<String> {
+    enumeration
+        .variants
+        .iter()
+        .map(|variant| format!("<{}>", options.resource_iri(enumeration.class, variant)))
+        .collect()
+}
+
+fn kdi(name: &str) -> String {
+    format!("kdi:{}", name)
+}
+
+fn range(range: &Range) -> String {
+    match range {
+        Range::Literal(datatype) => format!("xsd:{}", datatype.xsd()),
+        Range::Class(class) => kdi(class),
+    }
+}
+
+fn list(items: &[String]) -> String {
+    format!("( {} )", items.join(" "))
+}
+
+/// The only class of `classes`, or their `owl:unionOf`
+fn union(classes: &[String]) -> String {
+    match classes {
+        [class] => class.clone(),
+        _ => format!("[ a owl:Class ; owl:unionOf {} ]", list(classes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ontology_declares_the_schema() {
+        let options = RdfOptions::default();
+        let ttl = ontology(&options);
+        for (class, properties) in rdf::classes() {
+            assert!(ttl.contains(&format!("kdi:{} a owl:Class", class)));
+            for property in properties {
+                assert!(
+                    ttl.contains(&format!("kdi:{} a owl:", property.name)),
+                    "`{}.{}` not declared",
+                    class,
+                    property.name
+                );
+            }
+        }
+        for enumeration in rdf::ENUMERATIONS {
+            assert!(ttl.contains(&format!("kdi:{} a owl:Class", enumeration.class)));
+            for individual in individuals(&options, &enumeration) {
+                assert!(
+                    ttl.contains(&format!(
+                        "{} a owl:NamedIndividual, kdi:{} .",
+                        individual, enumeration.class
+                    )),
+                    "`{}` not declared",
+                    individual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_constrain_the_schema() {
+        let options = RdfOptions::default();
+        let ttl = shapes(&options);
+        for (class, properties) in rdf::classes() {
+            let header = format!(
+                "kdish:{} a sh:NodeShape ;\n    sh:targetClass kdi:{}",
+                class, class
+            );
+            let shape = ttl
+                .split("\n\n")
+                .find(|shape| shape.starts_with(&header))
+                .unwrap_or_else(|| panic!("no `{}` shape", class));
+            for property in properties {
+                assert!(
+                    shape.contains(&format!("sh:path kdi:{}", property.name)),
+                    "`{}.{}` not constrained",
+                    class,
+                    property.name
+                );
+            }
+        }
+        for enumeration in rdf::ENUMERATIONS {
+            // Open enumerations are only constrained to IRIs
+            if enumeration.open {
+                assert!(!ttl.contains(&format!("sh:class kdi:{}", enumeration.class)));
+                continue;
+            }
+            assert!(ttl.contains(&format!("sh:class kdi:{}", enumeration.class)));
+            for individual in individuals(&options, &enumeration) {
+                assert!(ttl.contains(&individual), "`{}` not allowed", individual);
+            }
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use flate2::write::GzEncoder;
+use log::{debug, info};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use crate::error::KdiError;
+
+/// Output file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// One `JSON` array per file
+    #[default]
+    Json,
+    /// One `JSON` object per line (`NDJSON`)
+    Jsonl,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Output file compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+        }
+    }
+
+    /// `file_name` with the compression extension, e.g. `stop_times.json.gz`
+    pub fn file_name(&self, file_name: &str) -> String {
+        match self.extension() {
+            Some(extension) => format!("{}.{}", file_name, extension),
+            None => file_name.to_string(),
+        }
+    }
+}
+
+/// How the aligned entities are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputOptions {
+    pub format: Format,
+    pub compression: Compression,
+    /// When set, every entity file is also split into `<name>_<n>` files of at most `chunk_size` records
+    pub chunk_size: Option<NonZeroUsize>,
+}
+
+impl OutputOptions {
+    /// File name of the `name` entity, e.g. `stop_times.jsonl.gz`
+    pub fn file_name(&self, name: &str) -> String {
+        self.compression
+            .file_name(&format!("{}.{}", name, self.format.extension()))
+    }
+
+    /// Write the `name` entity file under `output`, one record at a time
+    pub fn write_records<T: Serialize>(
+        &self,
+        output: &Path,
+        name: &str,
+        records: impl IntoIterator<Item = T>,
+    ) -> Result<(), KdiError> {
+        let mut writer = self.records_writer(output, name)?;
+        for record in records {
+            writer.write(&record)?;
+        }
+        writer.finish()
+    }
+
+    /// Writer of the `name` entity file under `output`
+    pub fn records_writer(&self, output: &Path, name: &str) -> Result<RecordsWriter, KdiError> {
+        info!("Writing `{}` file", self.file_name(name));
+        Ok(RecordsWriter {
+            options: *self,
+            output: output.to_path_buf(),
+            name: name.to_string(),
+            file: FileWriter::create(
+                &output.join(self.file_name(name)),
+                self.compression,
+                self.records_layout(),
+            )?,
+            chunk: None,
+            chunk_index: 0,
+            chunk_len: 0,
+        })
+    }
+
+    /// Write `value` as the single document of the `name` file under `output`
+    pub fn write_document<T: Serialize + ?Sized>(
+        &self,
+        output: &Path,
+        name: &str,
+        value: &T,
+    ) -> Result<(), KdiError> {
+        info!("Writing `{}` file", self.file_name(name));
+        let layout = match self.format {
+            Format::Json => Layout::Document,
+            Format::Jsonl => Layout::Lines,
+        };
+        let mut file =
+            FileWriter::create(&output.join(self.file_name(name)), self.compression, layout)?;
+        file.write_record(value)?;
+        file.finish()
+    }
+
+    fn records_layout(&self) -> Layout {
+        match self.format {
+            Format::Json => Layout::Array,
+            Format::Jsonl => Layout::Lines,
+        }
+    }
+}
+
+/// Entity file written one record at a time, split in chunks if requested
+pub struct RecordsWriter {
+    options: OutputOptions,
+    output: PathBuf,
+    name: String,
+    file: FileWriter,
+    chunk: Option<FileWriter>,
+    chunk_index: usize,
+    chunk_len: usize,
+}
+
+impl RecordsWriter {
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<(), KdiError> {
+        self.file.write_record(record)?;
+
+        let chunk_size = match self.options.chunk_size {
+            Some(chunk_size) => chunk_size.get(),
+            None => return Ok(()),
+        };
+        if self.chunk_len == chunk_size {
+            if let Some(full) = self.chunk.take() {
+                full.finish()?;
+            }
+            self.chunk_index += 1;
+            self.chunk_len = 0;
+        }
+        let chunk = match &mut self.chunk {
+            Some(chunk) => chunk,
+            None => {
+                let name = self
+                    .options
+                    .file_name(&format!("{}_{}", self.name, self.chunk_index));
+                debug!("Writing `{}` file", name);
+                self.chunk.insert(FileWriter::create(
+                    &self.output.join(name),
+                    self.options.compression,
+                    self.options.records_layout(),
+                )?)
+            }
+        };
+        chunk.write_record(record)?;
+        self.chunk_len += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), KdiError> {
+        self.file.finish()?;
+        if let Some(chunk) = self.chunk {
+            chunk.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How the values of a file are laid out
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// `JSON` array
+    Array,
+    /// One value per line
+    Lines,
+    /// Single value
+    Document,
+}
+
+/// Single `JSON` or `JSON Lines` output file
+struct FileWriter {
+    layout: Layout,
+    file: OutputFile,
+    empty: bool,
+}
+
+impl FileWriter {
+    fn create(
+        path: &Path,
+        compression: Compression,
+        layout: Layout,
+    ) -> Result<FileWriter, KdiError> {
+        let mut file = FileWriter {
+            layout,
+            file: OutputFile::create(path, compression)?,
+            empty: true,
+        };
+        if layout == Layout::Array {
+            file.file.write_raw(b"[")?;
+        }
+
+        Ok(file)
+    }
+
+    fn write_record<T: Serialize + ?Sized>(&mut self, record: &T) -> Result<(), KdiError> {
+        match self.layout {
+            Layout::Array => {
+                if !self.empty {
+                    self.file.write_raw(b",")?;
+                }
+                self.write_value(record)?;
+            }
+            Layout::Lines => {
+                self.write_value(record)?;
+                self.file.write_raw(b"\n")?;
+            }
+            Layout::Document => self.write_value(record)?,
+        }
+        self.empty = false;
+
+        Ok(())
+    }
+
+    fn write_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), KdiError> {
+        serde_json::to_writer(&mut self.file, value).map_err(|source| KdiError::Json {
+            file: self.file.path().to_path_buf(),
+            source,
+        })
+    }
+
+    fn finish(mut self) -> Result<(), KdiError> {
+        if self.layout == Layout::Array {
+            self.file.write_raw(b"]")?;
+        }
+        self.file.finish()
+    }
+}
+
+/// Buffered output file, compressed on the fly
+pub struct OutputFile {
+    path: PathBuf,
+    sink: Sink,
+}
+
+enum Sink {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl OutputFile {
+    pub fn create(path: &Path, compression: Compression) -> Result<OutputFile, KdiError> {
+        let io_error = |source| KdiError::Io {
+            file: path.to_path_buf(),
+            source,
+        };
+        let writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        let sink = match compression {
+            Compression::None => Sink::Plain(writer),
+            Compression::Gzip => Sink::Gzip(GzEncoder::new(writer, flate2::Compression::default())),
+            Compression::Zstd => Sink::Zstd(zstd::Encoder::new(writer, 0).map_err(io_error)?),
+        };
+
+        Ok(OutputFile {
+            path: path.to_path_buf(),
+            sink,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write all of `bytes`, mapping the failure to a [`KdiError::Io`]
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<(), KdiError> {
+        self.write_all(bytes).map_err(|source| KdiError::Io {
+            file: self.path.clone(),
+            source,
+        })
+    }
+
+    /// Complete the compressed stream and flush the file
+    pub fn finish(self) -> Result<(), KdiError> {
+        let path = self.path;
+        let io_error = |source| KdiError::Io { file: path, source };
+        let writer = match self.sink {
+            Sink::Plain(writer) => Ok(writer),
+            Sink::Gzip(encoder) => encoder.finish(),
+            Sink::Zstd(encoder) => encoder.finish(),
+        };
+        writer
+            .and_then(|mut writer| writer.flush())
+            .map_err(io_error)
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.sink {
+            Sink::Plain(writer) => writer.write(buf),
+            Sink::Gzip(writer) => writer.write(buf),
+            Sink::Zstd(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::Plain(writer) => writer.flush(),
+            Sink::Gzip(writer) => writer.flush(),
+            Sink::Zstd(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use serde_json::{json, Value};
+    use std::io::Read;
+
+    fn options(format: Format, compression: Compression, chunk_size: usize) -> OutputOptions {
+        OutputOptions {
+            format,
+            compression,
+            chunk_size: NonZeroUsize::new(chunk_size),
+        }
+    }
+
+    fn records(len: usize) -> Vec<Value> {
+        (0..len).map(|i| json!({ "id": i.to_string() })).collect()
+    }
+
+    /// Decompressed content of the `file_name` file under `output`
+    fn read(output: &Path, file_name: &str, compression: Compression) -> String {
+        let file = File::open(output.join(file_name)).unwrap();
+        let mut content = String::new();
+        match compression {
+            Compression::None => io::BufReader::new(file).read_to_string(&mut content),
+            Compression::Gzip => GzDecoder::new(file).read_to_string(&mut content),
+            Compression::Zstd => zstd::Decoder::new(file)
+                .unwrap()
+                .read_to_string(&mut content),
+        }
+        .unwrap();
+        content
+    }
+
+    #[test]
+    fn json_lines() {
+        let output = tempfile::tempdir().unwrap();
+        let options = options(Format::Jsonl, Compression::None, 0);
+        options
+            .write_records(output.path(), "fares", records(3))
+            .unwrap();
+
+        let content = read(output.path(), "fares.jsonl", Compression::None);
+        assert_eq!(content, "{\"id\":\"0\"}\n{\"id\":\"1\"}\n{\"id\":\"2\"}\n");
+    }
+
+    #[test]
+    fn empty_json_array() {
+        let output = tempfile::tempdir().unwrap();
+        let options = options(Format::Json, Compression::None, 0);
+        options
+            .write_records(output.path(), "fares", records(0))
+            .unwrap();
+
+        assert_eq!(read(output.path(), "fares.json", Compression::None), "[]");
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        let output = tempfile::tempdir().unwrap();
+        let options = options(Format::Json, Compression::None, 2);
+        options
+            .write_records(output.path(), "fares", records(5))
+            .unwrap();
+
+        let file = |name: &str| -> Value {
+            serde_json::from_str(&read(output.path(), name, Compression::None)).unwrap()
+        };
+        assert_eq!(file("fares.json"), Value::Array(records(5)));
+        assert_eq!(file("fares_0.json"), Value::Array(records(2)));
+        assert_eq!(file("fares_1.json"), json!([{ "id": "2" }, { "id": "3" }]));
+        assert_eq!(file("fares_2.json"), json!([{ "id": "4" }]));
+        assert!(!output.path().join("fares_3.json").exists());
+    }
+
+    #[test]
+    fn exact_chunks() {
+        let output = tempfile::tempdir().unwrap();
+        let options = options(Format::Jsonl, Compression::None, 2);
+        options
+            .write_records(output.path(), "fares", records(4))
+            .unwrap();
+
+        assert_eq!(
+            read(output.path(), "fares_1.jsonl", Compression::None),
+            "{\"id\":\"2\"}\n{\"id\":\"3\"}\n"
+        );
+        assert!(!output.path().join("fares_2.jsonl").exists());
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let output = tempfile::tempdir().unwrap();
+            let options = options(Format::Jsonl, compression, 2);
+            options
+                .write_records(output.path(), "fares", records(3))
+                .unwrap();
+
+            let lines = |name: &str| -> Vec<Value> {
+                read(output.path(), &options.file_name(name), compression)
+                    .lines()
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .collect()
+            };
+            assert_eq!(lines("fares"), records(3));
+            assert_eq!(lines("fares_0"), records(2));
+            assert_eq!(lines("fares_1"), [json!({ "id": "2" })]);
+        }
+    }
+
+    #[test]
+    fn document() {
+        let output = tempfile::tempdir().unwrap();
+        let options = options(Format::Json, Compression::Gzip, 1);
+        let context = json!({ "@context": { "id": "@id" } });
+        options
+            .write_document(output.path(), "context", &context)
+            .unwrap();
+
+        let content = read(output.path(), "context.json.gz", Compression::Gzip);
+        assert_eq!(serde_json::from_str::<Value>(&content).unwrap(), context);
+        assert!(!output.path().join("context_0.json.gz").exists());
+    }
+}
@@ -0,0 +1,859 @@
+use arrow::array::{
+    ArrayRef, BooleanArray, Date32Array, DictionaryArray, DurationSecondArray, Float64Array,
+    ListBuilder, StringArray, StringDictionaryBuilder, UInt64Array,
+};
+use arrow::datatypes::{DataType, Field, Int32Type, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use log::info;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{GzipLevel, ZstdLevel};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::fmt::Debug;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::error::{KdiError, Record};
+use crate::output::Compression;
+use crate::structs::{
+    KdiAgency, KdiBikeSharingStop, KdiCalendar, KdiCalendarException, KdiFare, KdiFareRule,
+    KdiLocation, KdiParkingStop, KdiPublicTransportStop, KdiRoute, KdiStopTime, KdiTrip,
+};
+
+/// Rows buffered before being written as a record batch
+const BATCH_SIZE: usize = 64 * 1024;
+
+/// Format of the aligned date times, e.g. `2021-09-13T00:00:00`
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Type of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    String,
+    /// Dictionary encoded enum variant
+    Enum,
+    /// Dictionary encoded enum variants
+    EnumList,
+    Double,
+    Count,
+    Boolean,
+    Date,
+    /// Seconds since the start of the service day, past `24:00:00` for trips running after midnight
+    Duration,
+}
+
+impl ColumnType {
+    fn data_type(&self) -> DataType {
+        let dictionary =
+            || DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+        match self {
+            ColumnType::String => DataType::Utf8,
+            ColumnType::Enum => dictionary(),
+            ColumnType::EnumList => {
+                DataType::List(Arc::new(Field::new("item", dictionary(), true)))
+            }
+            ColumnType::Double => DataType::Float64,
+            ColumnType::Count => DataType::UInt64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Date => DataType::Date32,
+            ColumnType::Duration => DataType::Duration(TimeUnit::Second),
+        }
+    }
+}
+
+/// Column of a table
+#[derive(Debug)]
+pub struct Column {
+    pub name: &'static str,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+}
+
+impl Column {
+    const fn new(name: &'static str, column_type: ColumnType) -> Column {
+        Column {
+            name,
+            column_type,
+            nullable: false,
+        }
+    }
+
+    const fn nullable(mut self) -> Column {
+        self.nullable = true;
+        self
+    }
+}
+
+const fn string(name: &'static str) -> Column {
+    Column::new(name, ColumnType::String)
+}
+
+const fn enumeration(name: &'static str) -> Column {
+    Column::new(name, ColumnType::Enum)
+}
+
+const fn double(name: &'static str) -> Column {
+    Column::new(name, ColumnType::Double)
+}
+
+const fn count(name: &'static str) -> Column {
+    Column::new(name, ColumnType::Count)
+}
+
+const fn boolean(name: &'static str) -> Column {
+    Column::new(name, ColumnType::Boolean)
+}
+
+const fn date(name: &'static str) -> Column {
+    Column::new(name, ColumnType::Date)
+}
+
+/// Table of an entity, written to the `<name>.parquet` file
+#[derive(Debug)]
+pub struct Table {
+    pub name: &'static str,
+    pub columns: &'static [Column],
+}
+
+impl Table {
+    fn schema(&self) -> SchemaRef {
+        let fields: Vec<Field> = self
+            .columns
+            .iter()
+            .map(|column| Field::new(column.name, column.column_type.data_type(), column.nullable))
+            .collect();
+        Arc::new(Schema::new(fields))
+    }
+}
+
+/// Value of a row
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    List(Vec<String>),
+    Double(f64),
+    Count(u64),
+    Boolean(bool),
+    /// Days since `1970-01-01`
+    Date(i32),
+    /// Seconds
+    Duration(i64),
+    Null,
+}
+
+/// Entity stored as a row of its table
+pub trait ToArrow {
+    const TABLE: &'static Table;
+
+    fn id(&self) -> &str;
+
+    /// Values of the row, in the order of the table columns
+    fn values(&self) -> Result<Vec<Value>, KdiError>;
+}
+
+/// `Parquet` file of an entity table, written one record batch at a time
+pub struct ParquetWriter<T: ToArrow> {
+    path: PathBuf,
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    columns: Vec<Vec<Value>>,
+    entity: PhantomData<T>,
+}
+
+impl<T: ToArrow> ParquetWriter<T> {
+    /// Create the file of the `T` table under `output`, compressing the pages with `compression`.
+    /// Pages are compressed with `Snappy` when there is no compression
+    pub fn create(output: &Path, compression: Compression) -> Result<ParquetWriter<T>, KdiError> {
+        let path = output.join(format!("{}.parquet", T::TABLE.name));
+        info!("Writing `{}` file", path.display());
+        let file = File::create(&path).map_err(|source| KdiError::Io {
+            file: path.clone(),
+            source,
+        })?;
+        let codec = match compression {
+            Compression::None => parquet::basic::Compression::SNAPPY,
+            Compression::Gzip => parquet::basic::Compression::GZIP(GzipLevel::default()),
+            Compression::Zstd => parquet::basic::Compression::ZSTD(ZstdLevel::default()),
+        };
+        let properties = WriterProperties::builder().set_compression(codec).build();
+        let schema = T::TABLE.schema();
+        let writer =
+            ArrowWriter::try_new(file, schema.clone(), Some(properties)).map_err(|source| {
+                KdiError::Parquet {
+                    file: path.clone(),
+                    source,
+                }
+            })?;
+
+        Ok(ParquetWriter {
+            path,
+            writer,
+            schema,
+            columns: T::TABLE.columns.iter().map(|_| Vec::new()).collect(),
+            entity: PhantomData,
+        })
+    }
+
+    pub fn write(&mut self, entity: &T) -> Result<(), KdiError> {
+        for (column, value) in self.columns.iter_mut().zip(entity.values()?) {
+            column.push(value);
+        }
+        if self.columns[0].len() == BATCH_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), KdiError> {
+        self.flush()?;
+        self.writer.close().map_err(|source| KdiError::Parquet {
+            file: self.path,
+            source,
+        })?;
+
+        Ok(())
+    }
+
+    /// Write the buffered rows as a record batch
+    fn flush(&mut self) -> Result<(), KdiError> {
+        if self.columns[0].is_empty() {
+            return Ok(());
+        }
+        let arrays: Vec<ArrayRef> = T::TABLE
+            .columns
+            .iter()
+            .zip(self.columns.iter_mut())
+            .map(|(column, values)| array(column.column_type, &std::mem::take(values)))
+            .collect();
+
+        RecordBatch::try_new(self.schema.clone(), arrays)
+            .map_err(ParquetError::from)
+            .and_then(|batch| self.writer.write(&batch))
+            .map_err(|source| KdiError::Parquet {
+                file: self.path.clone(),
+                source,
+            })
+    }
+}
+
+/// Write the file of the `T` table under `output`
+pub fn write<'e, T: ToArrow + 'e>(
+    output: &Path,
+    compression: Compression,
+    entities: impl IntoIterator<Item = &'e T>,
+) -> Result<(), KdiError> {
+    let mut writer = ParquetWriter::create(output, compression)?;
+    for entity in entities {
+        writer.write(entity)?;
+    }
+    writer.finish()
+}
+
+fn as_str(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Arrow array of the `values` of a `column_type` column
+fn array(column_type: ColumnType, values: &[Value]) -> ArrayRef {
+    match column_type {
+        ColumnType::String => Arc::new(values.iter().map(as_str).collect::<StringArray>()),
+        ColumnType::Enum => Arc::new(
+            values
+                .iter()
+                .map(as_str)
+                .collect::<DictionaryArray<Int32Type>>(),
+        ),
+        ColumnType::EnumList => {
+            let mut builder = ListBuilder::new(StringDictionaryBuilder::<Int32Type>::new());
+            for value in values {
+                match value {
+                    Value::List(variants) => {
+                        for variant in variants {
+                            builder.values().append_value(variant);
+                        }
+                        builder.append(true);
+                    }
+                    _ => builder.append(false),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Double => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Double(value) => Some(*value),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        ColumnType::Count => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Count(value) => Some(*value),
+                    _ => None,
+                })
+                .collect::<UInt64Array>(),
+        ),
+        ColumnType::Boolean => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Boolean(value) => Some(*value),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+        ColumnType::Date => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Date(value) => Some(*value),
+                    _ => None,
+                })
+                .collect::<Date32Array>(),
+        ),
+        ColumnType::Duration => Arc::new(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Duration(value) => Some(*value),
+                    _ => None,
+                })
+                .collect::<DurationSecondArray>(),
+        ),
+    }
+}
+
+// --- COMMON
+pub const LOCATIONS: Table = Table {
+    name: "locations",
+    columns: &[
+        string("id"),
+        string("name"),
+        double("latitude"),
+        double("longitude"),
+    ],
+};
+pub const CALENDAR_EXCEPTIONS: Table = Table {
+    name: "calendar_exceptions",
+    columns: &[
+        string("id"),
+        string("calendar"),
+        date("date"),
+        enumeration("exception"),
+    ],
+};
+pub const CALENDARS: Table = Table {
+    name: "calendars",
+    columns: &[
+        string("id"),
+        date("startDate"),
+        date("endDate"),
+        boolean("monday"),
+        boolean("tuesday"),
+        boolean("wednesday"),
+        boolean("thursday"),
+        boolean("friday"),
+        boolean("saturday"),
+        boolean("sunday"),
+    ],
+};
+pub const AGENCIES: Table = Table {
+    name: "agencies",
+    columns: &[
+        string("id"),
+        string("name"),
+        string("email"),
+        string("phone"),
+        string("url"),
+    ],
+};
+
+// --- CORE
+pub const FARE_RULES: Table = Table {
+    name: "fare_rules",
+    columns: &[
+        string("id"),
+        string("fare"),
+        string("route").nullable(),
+        string("origin").nullable(),
+        string("destination").nullable(),
+        string("contains").nullable(),
+    ],
+};
+pub const PARKING_STOPS: Table = Table {
+    name: "parking_stops",
+    columns: &[
+        string("id"),
+        string("location"),
+        enumeration("type"),
+        string("address"),
+        count("totalSlots"),
+    ],
+};
+pub const FARES: Table = Table {
+    name: "fares",
+    columns: &[
+        string("id"),
+        double("price"),
+        enumeration("currency"),
+        enumeration("type"),
+        enumeration("payment"),
+        Column::new("duration", ColumnType::Duration),
+    ],
+};
+pub const BIKE_SHARING_STOPS: Table = Table {
+    name: "bike_sharing_stops",
+    columns: &[
+        string("id"),
+        string("location"),
+        enumeration("type"),
+        string("address"),
+        count("totalSlots"),
+        count("freeSlots"),
+        count("bikes"),
+    ],
+};
+pub const STOP_TIMES: Table = Table {
+    name: "stop_times",
+    columns: &[
+        string("id"),
+        string("trip"),
+        string("stop"),
+        Column::new("arrival", ColumnType::Duration).nullable(),
+        Column::new("departure", ColumnType::Duration).nullable(),
+        count("sequence"),
+    ],
+};
+pub const TRIPS: Table = Table {
+    name: "trips",
+    columns: &[
+        string("id"),
+        string("route"),
+        string("calendar"),
+        string("name"),
+        enumeration("direction"),
+        enumeration("weelchair"),
+        enumeration("bike"),
+    ],
+};
+pub const ROUTES: Table = Table {
+    name: "routes",
+    columns: &[
+        string("id"),
+        string("agency"),
+        string("shortName"),
+        string("longName"),
+        enumeration("transport"),
+    ],
+};
+pub const PUBLIC_TRANSPORT_STOPS: Table = Table {
+    name: "public_transport_stops",
+    columns: &[
+        string("id"),
+        string("location"),
+        string("zone").nullable(),
+        Column::new("type", ColumnType::EnumList),
+        enumeration("weelchair"),
+    ],
+};
+
+fn text(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+fn variant(value: &impl Debug) -> Value {
+    Value::String(format!("{:?}", value))
+}
+
+fn optional(value: &Option<String>) -> Value {
+    value.as_deref().map_or(Value::Null, text)
+}
+
+/// Days since `1970-01-01` of an aligned date time
+fn days<T: ToArrow>(entity: &T, field: &'static str, value: &str) -> Result<Value, KdiError> {
+    let date_time = parse(entity, field, value)?;
+    let epoch = DateTime::UNIX_EPOCH.date_naive();
+    Ok(Value::Date((date_time.date() - epoch).num_days() as i32))
+}
+
+/// Seconds since the start of the service day of an aligned stop time,
+/// whose day counts from `0000-01-01`
+fn seconds<T: ToArrow>(
+    entity: &T,
+    field: &'static str,
+    value: &Option<String>,
+) -> Result<Value, KdiError> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(Value::Null),
+    };
+    let date_time = parse(entity, field, value)?;
+    let start = NaiveDate::from_ymd_opt(0, 1, 1)
+        .unwrap_or(NaiveDate::MIN)
+        .and_time(NaiveTime::MIN);
+    Ok(Value::Duration((date_time - start).num_seconds()))
+}
+
+fn parse<T: ToArrow>(
+    entity: &T,
+    field: &'static str,
+    value: &str,
+) -> Result<NaiveDateTime, KdiError> {
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .map_err(|_| invalid(entity, field, value))
+}
+
+fn invalid<T: ToArrow>(entity: &T, field: &'static str, value: &str) -> KdiError {
+    KdiError::InvalidField {
+        record: Record::new(Path::new(T::TABLE.name), None, Some(entity.id())),
+        field,
+        value: value.to_string(),
+    }
+}
+
+impl ToArrow for KdiLocation {
+    const TABLE: &'static Table = &LOCATIONS;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.name),
+            Value::Double(self.latitude),
+            Value::Double(self.longitude),
+        ])
+    }
+}
+
+impl ToArrow for KdiCalendarException {
+    const TABLE: &'static Table = &CALENDAR_EXCEPTIONS;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.calendar),
+            days(self, "date", &self.date)?,
+            variant(&self.exception),
+        ])
+    }
+}
+
+impl ToArrow for KdiCalendar {
+    const TABLE: &'static Table = &CALENDARS;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            days(self, "startDate", &self.start_date)?,
+            days(self, "endDate", &self.end_date)?,
+            Value::Boolean(self.monday),
+            Value::Boolean(self.tuesday),
+            Value::Boolean(self.wednesday),
+            Value::Boolean(self.thursday),
+            Value::Boolean(self.friday),
+            Value::Boolean(self.saturday),
+            Value::Boolean(self.sunday),
+        ])
+    }
+}
+
+impl ToArrow for KdiAgency<'_> {
+    const TABLE: &'static Table = &AGENCIES;
+
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(self.id),
+            text(self.name),
+            text(self.email),
+            text(self.phone),
+            text(self.url),
+        ])
+    }
+}
+
+impl ToArrow for KdiFareRule {
+    const TABLE: &'static Table = &FARE_RULES;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.fare),
+            optional(&self.route),
+            optional(&self.origin),
+            optional(&self.destination),
+            optional(&self.contains),
+        ])
+    }
+}
+
+impl ToArrow for KdiParkingStop {
+    const TABLE: &'static Table = &PARKING_STOPS;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.location),
+            variant(&self.ptype),
+            text(&self.address),
+            Value::Count(self.total_slots as u64),
+        ])
+    }
+}
+
+impl ToArrow for KdiFare {
+    const TABLE: &'static Table = &FARES;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            Value::Double(self.price),
+            variant(&self.currency),
+            variant(&self.ftype),
+            variant(&self.payment),
+            Value::Duration(self.duration as i64),
+        ])
+    }
+}
+
+impl ToArrow for KdiBikeSharingStop {
+    const TABLE: &'static Table = &BIKE_SHARING_STOPS;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.location),
+            variant(&self.ptype),
+            text(&self.address),
+            Value::Count(self.total_slots as u64),
+            Value::Count(self.free_slots as u64),
+            Value::Count(self.bikes as u64),
+        ])
+    }
+}
+
+impl ToArrow for KdiStopTime {
+    const TABLE: &'static Table = &STOP_TIMES;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.trip),
+            text(&self.stop),
+            seconds(self, "arrival", &self.arrival)?,
+            seconds(self, "departure", &self.departure)?,
+            Value::Count(self.sequence as u64),
+        ])
+    }
+}
+
+impl ToArrow for KdiTrip<'_> {
+    const TABLE: &'static Table = &TRIPS;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.route),
+            text(&self.calendar),
+            text(self.name),
+            variant(&self.direction),
+            variant(&self.weelchair),
+            variant(&self.bike),
+        ])
+    }
+}
+
+impl ToArrow for KdiRoute<'_> {
+    const TABLE: &'static Table = &ROUTES;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(self.agency),
+            text(self.short_name),
+            text(self.long_name),
+            variant(&self.transport),
+        ])
+    }
+}
+
+impl ToArrow for KdiPublicTransportStop {
+    const TABLE: &'static Table = &PUBLIC_TRANSPORT_STOPS;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn values(&self) -> Result<Vec<Value>, KdiError> {
+        Ok(vec![
+            text(&self.id),
+            text(&self.location),
+            optional(&self.zone),
+            Value::List(self.ptype.iter().map(|t| format!("{:?}", t)).collect()),
+            variant(&self.weelchair),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{
+        Array, BooleanArray, Date32Array, DurationSecondArray, StringArray, UInt64Array,
+    };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    /// Single record batch of the `T` table written under `output`
+    fn read<T: ToArrow>(output: &Path) -> RecordBatch {
+        let file = File::open(output.join(format!("{}.parquet", T::TABLE.name))).unwrap();
+        let mut batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(batches.len(), 1);
+        batches.remove(0)
+    }
+
+    fn column<'b, A: 'static>(batch: &'b RecordBatch, name: &str) -> &'b A {
+        batch
+            .column_by_name(name)
+            .unwrap()
+            .as_any()
+            .downcast_ref()
+            .unwrap()
+    }
+
+    fn calendar(id: &str, start_date: &str) -> KdiCalendar {
+        KdiCalendar {
+            id: id.to_string(),
+            start_date: start_date.to_string(),
+            end_date: "2022-06-10T00:00:00".to_string(),
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: false,
+            sunday: false,
+        }
+    }
+
+    #[test]
+    fn calendars_round_trip() {
+        let output = tempfile::tempdir().unwrap();
+        let calendars = [calendar("U_1", "2021-09-13T00:00:00")];
+        write(output.path(), Compression::Zstd, &calendars).unwrap();
+
+        let batch = read::<KdiCalendar>(output.path());
+        assert_eq!(batch.schema(), CALENDARS.schema());
+        assert_eq!(column::<StringArray>(&batch, "id").value(0), "U_1");
+        assert_eq!(column::<Date32Array>(&batch, "startDate").value(0), 18883);
+        assert_eq!(column::<Date32Array>(&batch, "endDate").value(0), 19153);
+        assert!(column::<BooleanArray>(&batch, "friday").value(0));
+        assert!(!column::<BooleanArray>(&batch, "saturday").value(0));
+    }
+
+    #[test]
+    fn stop_times_round_trip() {
+        let output = tempfile::tempdir().unwrap();
+        let stop_times = [
+            KdiStopTime {
+                id: "U_1_1".to_string(),
+                trip: "U_1".to_string(),
+                stop: "U_10".to_string(),
+                arrival: None,
+                departure: Some("0000-01-01T23:50:00".to_string()),
+                sequence: 1,
+            },
+            KdiStopTime {
+                id: "U_1_2".to_string(),
+                trip: "U_1".to_string(),
+                stop: "U_11".to_string(),
+                arrival: Some("0000-01-02T00:10:30".to_string()),
+                departure: None,
+                sequence: 2,
+            },
+        ];
+        write(output.path(), Compression::Gzip, &stop_times).unwrap();
+
+        let batch = read::<KdiStopTime>(output.path());
+        assert_eq!(batch.schema(), STOP_TIMES.schema());
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(column::<StringArray>(&batch, "stop").value(1), "U_11");
+        let arrival = column::<DurationSecondArray>(&batch, "arrival");
+        assert!(arrival.is_null(0));
+        assert_eq!(arrival.value(1), 24 * 3600 + 10 * 60 + 30);
+        let departure = column::<DurationSecondArray>(&batch, "departure");
+        assert_eq!(departure.value(0), 23 * 3600 + 50 * 60);
+        assert!(departure.is_null(1));
+        assert_eq!(column::<UInt64Array>(&batch, "sequence").values(), &[1, 2]);
+    }
+
+    #[test]
+    fn invalid_date() {
+        let output = tempfile::tempdir().unwrap();
+        let calendars = [calendar("U_1", "20210913")];
+        let error = write(output.path(), Compression::None, &calendars).unwrap_err();
+        assert!(matches!(
+            error,
+            KdiError::InvalidField {
+                field: "startDate",
+                ..
+            }
+        ));
+    }
+}
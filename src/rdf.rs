@@ -0,0 +1,1073 @@
+use log::info;
+use serde_json::{json, Map, Value};
+use std::fmt::{Debug, Write as _};
+use std::path::Path;
+use strum::VariantNames;
+
+use crate::enums::{
+    KdiCurrencyEnum, KdiDirectionEnum, KdiExceptionEnum, KdiFareEnum, KdiParkingStopEnum,
+    KdiPaymentEnum, KdiSupportedEnum, KdiTransportEnum,
+};
+use crate::error::KdiError;
+use crate::output::{Compression, OutputFile};
+use crate::structs::{
+    KdiAgency, KdiBikeSharingStop, KdiCalendar, KdiCalendarException, KdiFare, KdiFareRule,
+    KdiLocation, KdiParkingStop, KdiPublicTransportStop, KdiRoute, KdiStopTime, KdiTrip,
+};
+
+pub const DEFAULT_BASE_IRI: &str = "http://example.org/kdi/";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// `JSON-LD` context shared by every `JSON-LD` file
+pub const JSON_LD_CONTEXT: &str = "context";
+
+/// `RDF` serialization syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RdfFormat {
+    #[default]
+    Turtle,
+    NTriples,
+    /// `JSON-LD` documents with an `@graph` of nodes, sharing the generated [`JSON_LD_CONTEXT`]
+    JsonLd,
+}
+
+impl RdfFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RdfFormat::Turtle => "ttl",
+            RdfFormat::NTriples => "nt",
+            RdfFormat::JsonLd => "jsonld",
+        }
+    }
+}
+
+/// How the aligned entities are written as `RDF`.
+/// Entities are identified by `<base_iri><Class>/<id>`, classes and properties by `<base_iri>ontology/<name>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdfOptions {
+    pub format: RdfFormat,
+    pub compression: Compression,
+    pub base_iri: String,
+}
+
+impl Default for RdfOptions {
+    fn default() -> Self {
+        RdfOptions {
+            format: RdfFormat::default(),
+            compression: Compression::default(),
+            base_iri: DEFAULT_BASE_IRI.to_string(),
+        }
+    }
+}
+
+impl RdfOptions {
+    /// File name of the `name` entity, e.g. `stop_times.ttl`
+    pub fn file_name(&self, name: &str) -> String {
+        self.compression
+            .file_name(&format!("{}.{}", name, self.format.extension()))
+    }
+
+    /// Writer of the `name` entity file under `output`
+    pub fn writer(&self, output: &Path, name: &str) -> Result<RdfWriter<'_>, KdiError> {
+        info!("Writing `{}` file", self.file_name(name));
+        RdfWriter::create(&output.join(self.file_name(name)), self)
+    }
+
+    /// Write the `name` entity file under `output`
+    pub fn write_entities<'e, T: ToRdf + 'e>(
+        &self,
+        output: &Path,
+        name: &str,
+        entities: impl IntoIterator<Item = &'e T>,
+    ) -> Result<(), KdiError> {
+        let mut writer = self.writer(output, name)?;
+        for entity in entities {
+            writer.write(entity)?;
+        }
+        writer.finish()
+    }
+
+    /// Namespace of the classes and properties
+    pub fn ontology_iri(&self) -> String {
+        format!("{}ontology/", self.base_iri)
+    }
+
+    /// `IRI` of the `id` resource of `class`
+    pub fn resource_iri(&self, class: &str, id: &str) -> String {
+        format!("{}{}/{}", self.base_iri, class, encode_iri_segment(id))
+    }
+
+    /// `JSON-LD` context of every class and property of the schema
+    pub fn json_ld_context(&self) -> Value {
+        let mut context = Map::new();
+        context.insert("kdi".to_string(), json!(self.ontology_iri()));
+        context.insert("xsd".to_string(), json!(XSD));
+        for enumeration in ENUMERATIONS {
+            let class = enumeration.class;
+            context.insert(class.to_string(), json!(format!("kdi:{}", class)));
+        }
+        for (class, properties) in classes() {
+            context.insert(class.to_string(), json!(format!("kdi:{}", class)));
+            for property in properties {
+                let id = format!("kdi:{}", property.name);
+                let term = match property.range {
+                    Range::Class(_) => json!({ "@id": id, "@type": "@id" }),
+                    Range::Literal(Datatype::String) => json!(id),
+                    Range::Literal(datatype) => {
+                        json!({ "@id": id, "@type": format!("xsd:{}", datatype.xsd()) })
+                    }
+                };
+                context.entry(property.name).or_insert(term);
+            }
+        }
+
+        json!({ "@context": context })
+    }
+}
+
+/// Literal datatype
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Datatype {
+    String,
+    Integer,
+    Double,
+    Boolean,
+    DateTime,
+    AnyUri,
+}
+
+impl Datatype {
+    /// `XSD` local name
+    pub fn xsd(&self) -> &'static str {
+        match self {
+            Datatype::String => "string",
+            Datatype::Integer => "integer",
+            Datatype::Double => "double",
+            Datatype::Boolean => "boolean",
+            Datatype::DateTime => "dateTime",
+            Datatype::AnyUri => "anyURI",
+        }
+    }
+}
+
+/// Range of a property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Range {
+    Literal(Datatype),
+    /// Resource of a class or individual of an enumeration
+    Class(&'static str),
+}
+
+/// Property of a class of the schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Property {
+    pub name: &'static str,
+    pub range: Range,
+    /// Whether the property may be missing
+    pub optional: bool,
+    /// Whether the property may have more than one value
+    pub multiple: bool,
+}
+
+impl Property {
+    pub const fn literal(name: &'static str, datatype: Datatype) -> Property {
+        Property {
+            name,
+            range: Range::Literal(datatype),
+            optional: false,
+            multiple: false,
+        }
+    }
+
+    pub const fn resource(name: &'static str, class: &'static str) -> Property {
+        Property {
+            name,
+            range: Range::Class(class),
+            optional: false,
+            multiple: false,
+        }
+    }
+
+    pub const fn optional(self) -> Property {
+        Property {
+            optional: true,
+            ..self
+        }
+    }
+
+    pub const fn multiple(self) -> Property {
+        Property {
+            multiple: true,
+            ..self
+        }
+    }
+}
+
+/// Literal value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    String(String),
+    Integer(usize),
+    Double(f64),
+    Boolean(bool),
+    DateTime(String),
+    AnyUri(String),
+}
+
+impl Literal {
+    pub fn datatype(&self) -> Datatype {
+        match self {
+            Literal::String(_) => Datatype::String,
+            Literal::Integer(_) => Datatype::Integer,
+            Literal::Double(_) => Datatype::Double,
+            Literal::Boolean(_) => Datatype::Boolean,
+            Literal::DateTime(_) => Datatype::DateTime,
+            Literal::AnyUri(_) => Datatype::AnyUri,
+        }
+    }
+
+    /// `XSD` lexical form
+    pub fn lexical(&self) -> String {
+        match self {
+            Literal::String(value) | Literal::DateTime(value) | Literal::AnyUri(value) => {
+                value.clone()
+            }
+            Literal::Integer(value) => value.to_string(),
+            Literal::Double(value) if value.is_infinite() => {
+                if *value > 0.0 { "INF" } else { "-INF" }.to_string()
+            }
+            Literal::Double(value) => value.to_string(),
+            Literal::Boolean(value) => value.to_string(),
+        }
+    }
+
+    /// `JSON` value, typed by the `JSON-LD` context
+    pub fn to_json(&self) -> Value {
+        match self {
+            Literal::String(value) | Literal::DateTime(value) | Literal::AnyUri(value) => {
+                json!(value)
+            }
+            Literal::Integer(value) => json!(value),
+            Literal::Double(value) if value.is_finite() => json!(value),
+            Literal::Double(_) => json!(self.lexical()),
+            Literal::Boolean(value) => json!(value),
+        }
+    }
+}
+
+/// Object of a triple
+#[derive(Debug, Clone, PartialEq)]
+pub enum Object {
+    /// The `id` resource of `class`
+    Resource {
+        class: &'static str,
+        id: String,
+    },
+    Literal(Literal),
+}
+
+impl Object {
+    pub fn resource(class: &'static str, id: &str) -> Object {
+        Object::Resource {
+            class,
+            id: id.to_string(),
+        }
+    }
+
+    /// Individual of the `class` enumeration
+    pub fn individual<T: Debug>(class: &'static str, value: &T) -> Object {
+        Object::Resource {
+            class,
+            id: format!("{:?}", value),
+        }
+    }
+
+    pub fn string(value: &str) -> Object {
+        Object::Literal(Literal::String(value.to_string()))
+    }
+
+    pub fn integer(value: usize) -> Object {
+        Object::Literal(Literal::Integer(value))
+    }
+
+    pub fn double(value: f64) -> Object {
+        Object::Literal(Literal::Double(value))
+    }
+
+    pub fn boolean(value: bool) -> Object {
+        Object::Literal(Literal::Boolean(value))
+    }
+
+    pub fn date_time(value: &str) -> Object {
+        Object::Literal(Literal::DateTime(value.to_string()))
+    }
+
+    pub fn any_uri(value: &str) -> Object {
+        Object::Literal(Literal::AnyUri(value.to_string()))
+    }
+}
+
+/// Entity that can be described by `RDF` triples
+pub trait ToRdf {
+    /// Class of the entity, e.g. `Location`
+    const CLASS: &'static str;
+    /// Properties of the class
+    const PROPERTIES: &'static [Property];
+
+    fn id(&self) -> &str;
+
+    /// Property values of the entity, by property name.
+    /// References to other entities are [`Object::Resource`]s
+    fn properties(&self) -> Vec<(&'static str, Object)>;
+}
+
+/// Fare zone referenced by fare rules and public transport stops
+pub const ZONE_CLASS: &str = "Zone";
+
+/// Classes of the schema with their properties
+pub fn classes() -> [(&'static str, &'static [Property]); 13] {
+    [
+        (KdiLocation::CLASS, KdiLocation::PROPERTIES),
+        (
+            KdiCalendarException::CLASS,
+            KdiCalendarException::PROPERTIES,
+        ),
+        (KdiCalendar::CLASS, KdiCalendar::PROPERTIES),
+        (KdiAgency::CLASS, KdiAgency::PROPERTIES),
+        (KdiFareRule::CLASS, KdiFareRule::PROPERTIES),
+        (KdiFare::CLASS, KdiFare::PROPERTIES),
+        (KdiParkingStop::CLASS, KdiParkingStop::PROPERTIES),
+        (KdiBikeSharingStop::CLASS, KdiBikeSharingStop::PROPERTIES),
+        (
+            KdiPublicTransportStop::CLASS,
+            KdiPublicTransportStop::PROPERTIES,
+        ),
+        (KdiStopTime::CLASS, KdiStopTime::PROPERTIES),
+        (KdiTrip::CLASS, KdiTrip::PROPERTIES),
+        (KdiRoute::CLASS, KdiRoute::PROPERTIES),
+        (ZONE_CLASS, &[]),
+    ]
+}
+
+/// Enumeration class of the schema, its individuals are the enum variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Enumeration {
+    pub class: &'static str,
+    pub variants: &'static [&'static str],
+    /// Whether values other than `variants` are allowed, e.g. `TransportEnum::Other(1500)`
+    pub open: bool,
+}
+
+impl Enumeration {
+    const fn closed(class: &'static str, variants: &'static [&'static str]) -> Enumeration {
+        Enumeration {
+            class,
+            variants,
+            open: false,
+        }
+    }
+}
+
+/// Enumerations of the schema
+pub const ENUMERATIONS: [Enumeration; 8] = [
+    Enumeration::closed("PaymentEnum", KdiPaymentEnum::VARIANTS),
+    Enumeration::closed("ParkingStopEnum", KdiParkingStopEnum::VARIANTS),
+    Enumeration::closed("CurrencyEnum", KdiCurrencyEnum::VARIANTS),
+    Enumeration::closed("FareEnum", KdiFareEnum::VARIANTS),
+    Enumeration::closed("SupportedEnum", KdiSupportedEnum::VARIANTS),
+    Enumeration::closed("DirectionEnum", KdiDirectionEnum::VARIANTS),
+    Enumeration::closed("ExceptionEnum", KdiExceptionEnum::VARIANTS),
+    Enumeration {
+        class: "TransportEnum",
+        variants: KdiTransportEnum::VARIANTS,
+        open: true,
+    },
+];
+
+/// `RDF` file written one entity at a time
+pub struct RdfWriter<'a> {
+    options: &'a RdfOptions,
+    ontology: String,
+    file: OutputFile,
+    buffer: String,
+    empty: bool,
+}
+
+impl<'a> RdfWriter<'a> {
+    pub fn create(path: &Path, options: &'a RdfOptions) -> Result<RdfWriter<'a>, KdiError> {
+        let mut writer = RdfWriter {
+            options,
+            ontology: options.ontology_iri(),
+            file: OutputFile::create(path, options.compression)?,
+            buffer: String::new(),
+            empty: true,
+        };
+        let header = match options.format {
+            RdfFormat::Turtle => format!(
+                "@prefix kdi: <{}> .\n@prefix xsd: <{}> .\n\n",
+                writer.ontology, XSD
+            ),
+            RdfFormat::NTriples => String::new(),
+            RdfFormat::JsonLd => {
+                format!("{{\"@context\":\"{}.jsonld\",\"@graph\":[", JSON_LD_CONTEXT)
+            }
+        };
+        writer.file.write_raw(header.as_bytes())?;
+
+        Ok(writer)
+    }
+
+    /// Write the triples of `entity`
+    pub fn write<T: ToRdf>(&mut self, entity: &T) -> Result<(), KdiError> {
+        let subject = self.options.resource_iri(T::CLASS, entity.id());
+        self.write_triples(&subject, T::CLASS, &entity.properties())
+    }
+
+    /// Write the `rdf:type` triple of the `id` resource of `class`,
+    /// for resources without properties such as enumeration individuals and zones
+    pub fn write_resource(&mut self, class: &str, id: &str) -> Result<(), KdiError> {
+        let subject = self.options.resource_iri(class, id);
+        self.write_triples(&subject, class, &[])
+    }
+
+    pub fn finish(mut self) -> Result<(), KdiError> {
+        if self.options.format == RdfFormat::JsonLd {
+            self.file.write_raw(b"]}")?;
+        }
+        self.file.finish()
+    }
+
+    fn write_triples(
+        &mut self,
+        subject: &str,
+        class: &str,
+        properties: &[(&'static str, Object)],
+    ) -> Result<(), KdiError> {
+        self.buffer.clear();
+        match self.options.format {
+            RdfFormat::Turtle => {
+                let _ = write!(self.buffer, "<{}> a kdi:{}", subject, class);
+                for (property, object) in properties {
+                    let _ = write!(self.buffer, " ;\n    kdi:{} ", property);
+                    self.push_object(object);
+                }
+                self.buffer.push_str(" .\n");
+            }
+            RdfFormat::NTriples => {
+                let _ = writeln!(
+                    self.buffer,
+                    "<{}> <{}> <{}{}> .",
+                    subject, RDF_TYPE, self.ontology, class
+                );
+                for (property, object) in properties {
+                    let _ = write!(
+                        self.buffer,
+                        "<{}> <{}{}> ",
+                        subject, self.ontology, property
+                    );
+                    self.push_object(object);
+                    self.buffer.push_str(" .\n");
+                }
+            }
+            RdfFormat::JsonLd => {
+                if !self.empty {
+                    self.buffer.push(',');
+                }
+                let node = self.json_ld_node(subject, class, properties);
+                self.buffer.push_str(&node.to_string());
+            }
+        }
+        self.empty = false;
+
+        self.file.write_raw(self.buffer.as_bytes())
+    }
+
+    fn push_object(&mut self, object: &Object) {
+        match object {
+            Object::Resource { class, id } => {
+                let iri = self.options.resource_iri(class, id);
+                let _ = write!(self.buffer, "<{}>", iri);
+            }
+            Object::Literal(literal) => {
+                self.buffer.push('"');
+                escape_literal(&mut self.buffer, &literal.lexical());
+                self.buffer.push('"');
+                match (literal.datatype(), self.options.format) {
+                    (Datatype::String, _) => {}
+                    (datatype, RdfFormat::Turtle) => {
+                        let _ = write!(self.buffer, "^^xsd:{}", datatype.xsd());
+                    }
+                    (datatype, _) => {
+                        let _ = write!(self.buffer, "^^<{}{}>", XSD, datatype.xsd());
+                    }
+                }
+            }
+        }
+    }
+
+    /// `JSON-LD` node, repeated properties are grouped in arrays
+    fn json_ld_node(
+        &self,
+        subject: &str,
+        class: &str,
+        properties: &[(&'static str, Object)],
+    ) -> Value {
+        let mut node = Map::new();
+        node.insert("@id".to_string(), json!(subject));
+        node.insert("@type".to_string(), json!(class));
+        for (property, object) in properties {
+            let value = match object {
+                Object::Resource { class, id } => json!(self.options.resource_iri(class, id)),
+                Object::Literal(literal) => literal.to_json(),
+            };
+            match node.get_mut(*property) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(first) => *first = json!([first.take(), value]),
+                None => {
+                    node.insert(property.to_string(), value);
+                }
+            }
+        }
+
+        Value::Object(node)
+    }
+}
+
+/// Percent-encode every character of `id` that is not unreserved
+fn encode_iri_segment(id: &str) -> String {
+    let mut encoded = String::with_capacity(id.len());
+    for byte in id.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{:02X}", byte);
+            }
+        }
+    }
+    encoded
+}
+
+fn escape_literal(buffer: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            _ => buffer.push(c),
+        }
+    }
+}
+
+// Common
+impl ToRdf for KdiLocation {
+    const CLASS: &'static str = "Location";
+    const PROPERTIES: &'static [Property] = &[
+        Property::literal("name", Datatype::String),
+        Property::literal("latitude", Datatype::Double),
+        Property::literal("longitude", Datatype::Double),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            ("name", Object::string(&self.name)),
+            ("latitude", Object::double(self.latitude)),
+            ("longitude", Object::double(self.longitude)),
+        ]
+    }
+}
+
+impl ToRdf for KdiCalendarException {
+    const CLASS: &'static str = "CalendarException";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("calendar", KdiCalendar::CLASS),
+        Property::literal("date", Datatype::DateTime),
+        Property::resource("exception", "ExceptionEnum"),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            (
+                "calendar",
+                Object::resource(KdiCalendar::CLASS, &self.calendar),
+            ),
+            ("date", Object::date_time(&self.date)),
+            (
+                "exception",
+                Object::individual("ExceptionEnum", &self.exception),
+            ),
+        ]
+    }
+}
+
+impl ToRdf for KdiCalendar {
+    const CLASS: &'static str = "Calendar";
+    const PROPERTIES: &'static [Property] = &[
+        Property::literal("startDate", Datatype::DateTime),
+        Property::literal("endDate", Datatype::DateTime),
+        Property::literal("monday", Datatype::Boolean),
+        Property::literal("tuesday", Datatype::Boolean),
+        Property::literal("wednesday", Datatype::Boolean),
+        Property::literal("thursday", Datatype::Boolean),
+        Property::literal("friday", Datatype::Boolean),
+        Property::literal("saturday", Datatype::Boolean),
+        Property::literal("sunday", Datatype::Boolean),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            ("startDate", Object::date_time(&self.start_date)),
+            ("endDate", Object::date_time(&self.end_date)),
+            ("monday", Object::boolean(self.monday)),
+            ("tuesday", Object::boolean(self.tuesday)),
+            ("wednesday", Object::boolean(self.wednesday)),
+            ("thursday", Object::boolean(self.thursday)),
+            ("friday", Object::boolean(self.friday)),
+            ("saturday", Object::boolean(self.saturday)),
+            ("sunday", Object::boolean(self.sunday)),
+        ]
+    }
+}
+
+impl ToRdf for KdiAgency<'_> {
+    const CLASS: &'static str = "Agency";
+    const PROPERTIES: &'static [Property] = &[
+        Property::literal("name", Datatype::String),
+        Property::literal("email", Datatype::String),
+        Property::literal("phone", Datatype::String),
+        Property::literal("url", Datatype::AnyUri),
+    ];
+
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            ("name", Object::string(self.name)),
+            ("email", Object::string(self.email)),
+            ("phone", Object::string(self.phone)),
+            ("url", Object::any_uri(self.url)),
+        ]
+    }
+}
+
+// Core
+impl ToRdf for KdiFareRule {
+    const CLASS: &'static str = "FareRule";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("fare", KdiFare::CLASS),
+        Property::resource("route", KdiRoute::CLASS).optional(),
+        Property::resource("origin", ZONE_CLASS).optional(),
+        Property::resource("destination", ZONE_CLASS).optional(),
+        Property::resource("contains", ZONE_CLASS).optional(),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        let mut properties = vec![("fare", Object::resource(KdiFare::CLASS, &self.fare))];
+        if let Some(route) = &self.route {
+            properties.push(("route", Object::resource(KdiRoute::CLASS, route)));
+        }
+        for (name, zone) in [
+            ("origin", &self.origin),
+            ("destination", &self.destination),
+            ("contains", &self.contains),
+        ] {
+            if let Some(zone) = zone {
+                properties.push((name, Object::resource(ZONE_CLASS, zone)));
+            }
+        }
+        properties
+    }
+}
+
+impl ToRdf for KdiFare {
+    const CLASS: &'static str = "Fare";
+    const PROPERTIES: &'static [Property] = &[
+        Property::literal("price", Datatype::Double),
+        Property::resource("currency", "CurrencyEnum"),
+        Property::resource("type", "FareEnum"),
+        Property::resource("payment", "PaymentEnum"),
+        Property::literal("duration", Datatype::Integer),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            ("price", Object::double(self.price)),
+            (
+                "currency",
+                Object::individual("CurrencyEnum", &self.currency),
+            ),
+            ("type", Object::individual("FareEnum", &self.ftype)),
+            ("payment", Object::individual("PaymentEnum", &self.payment)),
+            ("duration", Object::integer(self.duration)),
+        ]
+    }
+}
+
+impl ToRdf for KdiParkingStop {
+    const CLASS: &'static str = "ParkingStop";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("location", KdiLocation::CLASS),
+        Property::resource("type", "ParkingStopEnum"),
+        Property::literal("address", Datatype::String),
+        Property::literal("totalSlots", Datatype::Integer),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            (
+                "location",
+                Object::resource(KdiLocation::CLASS, &self.location),
+            ),
+            ("type", Object::individual("ParkingStopEnum", &self.ptype)),
+            ("address", Object::string(&self.address)),
+            ("totalSlots", Object::integer(self.total_slots)),
+        ]
+    }
+}
+
+impl ToRdf for KdiBikeSharingStop {
+    const CLASS: &'static str = "BikeSharingStop";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("location", KdiLocation::CLASS),
+        Property::resource("type", "ParkingStopEnum"),
+        Property::literal("address", Datatype::String),
+        Property::literal("totalSlots", Datatype::Integer),
+        Property::literal("freeSlots", Datatype::Integer),
+        Property::literal("bikes", Datatype::Integer),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            (
+                "location",
+                Object::resource(KdiLocation::CLASS, &self.location),
+            ),
+            ("type", Object::individual("ParkingStopEnum", &self.ptype)),
+            ("address", Object::string(&self.address)),
+            ("totalSlots", Object::integer(self.total_slots)),
+            ("freeSlots", Object::integer(self.free_slots)),
+            ("bikes", Object::integer(self.bikes)),
+        ]
+    }
+}
+
+impl ToRdf for KdiPublicTransportStop {
+    const CLASS: &'static str = "PublicTransportStop";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("location", KdiLocation::CLASS),
+        Property::resource("zone", ZONE_CLASS).optional(),
+        Property::resource("type", "TransportEnum")
+            .optional()
+            .multiple(),
+        Property::resource("weelchair", "SupportedEnum"),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        let mut properties = vec![(
+            "location",
+            Object::resource(KdiLocation::CLASS, &self.location),
+        )];
+        if let Some(zone) = &self.zone {
+            properties.push(("zone", Object::resource(ZONE_CLASS, zone)));
+        }
+        for transport in &self.ptype {
+            properties.push(("type", Object::individual("TransportEnum", transport)));
+        }
+        properties.push((
+            "weelchair",
+            Object::individual("SupportedEnum", &self.weelchair),
+        ));
+        properties
+    }
+}
+
+impl ToRdf for KdiStopTime {
+    const CLASS: &'static str = "StopTime";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("trip", KdiTrip::CLASS),
+        Property::resource("stop", KdiPublicTransportStop::CLASS),
+        Property::literal("arrival", Datatype::DateTime).optional(),
+        Property::literal("departure", Datatype::DateTime).optional(),
+        Property::literal("sequence", Datatype::Integer),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        let mut properties = vec![
+            ("trip", Object::resource(KdiTrip::CLASS, &self.trip)),
+            (
+                "stop",
+                Object::resource(KdiPublicTransportStop::CLASS, &self.stop),
+            ),
+        ];
+        if let Some(arrival) = &self.arrival {
+            properties.push(("arrival", Object::date_time(arrival)));
+        }
+        if let Some(departure) = &self.departure {
+            properties.push(("departure", Object::date_time(departure)));
+        }
+        properties.push(("sequence", Object::integer(self.sequence)));
+        properties
+    }
+}
+
+impl ToRdf for KdiTrip<'_> {
+    const CLASS: &'static str = "Trip";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("route", KdiRoute::CLASS),
+        Property::resource("calendar", KdiCalendar::CLASS),
+        Property::literal("name", Datatype::String),
+        Property::resource("direction", "DirectionEnum"),
+        Property::resource("weelchair", "SupportedEnum"),
+        Property::resource("bike", "SupportedEnum"),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            ("route", Object::resource(KdiRoute::CLASS, &self.route)),
+            (
+                "calendar",
+                Object::resource(KdiCalendar::CLASS, &self.calendar),
+            ),
+            ("name", Object::string(self.name)),
+            (
+                "direction",
+                Object::individual("DirectionEnum", &self.direction),
+            ),
+            (
+                "weelchair",
+                Object::individual("SupportedEnum", &self.weelchair),
+            ),
+            ("bike", Object::individual("SupportedEnum", &self.bike)),
+        ]
+    }
+}
+
+impl ToRdf for KdiRoute<'_> {
+    const CLASS: &'static str = "Route";
+    const PROPERTIES: &'static [Property] = &[
+        Property::resource("agency", KdiAgency::CLASS),
+        Property::literal("shortName", Datatype::String),
+        Property::literal("longName", Datatype::String),
+        Property::resource("transport", "TransportEnum"),
+    ];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn properties(&self) -> Vec<(&'static str, Object)> {
+        vec![
+            ("agency", Object::resource(KdiAgency::CLASS, self.agency)),
+            ("shortName", Object::string(self.short_name)),
+            ("longName", Object::string(self.long_name)),
+            (
+                "transport",
+                Object::individual("TransportEnum", &self.transport),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::align::TT;
+    use crate::alignment::{Alignment, Group};
+    use crate::config::{Config, KmlLayer, Source};
+    use crate::source::Sources;
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn location(name: &str) -> KdiLocation {
+        KdiLocation {
+            id: "U_1 a/è".to_string(),
+            name: name.to_string(),
+            latitude: 46.07,
+            longitude: 11.12,
+        }
+    }
+
+    /// Content of the `locations` file of `location` written as `format`
+    fn write(format: RdfFormat, location: &KdiLocation) -> String {
+        let output = tempfile::tempdir().unwrap();
+        let options = RdfOptions {
+            format,
+            ..RdfOptions::default()
+        };
+        options
+            .write_entities(output.path(), "locations", [location])
+            .unwrap();
+        fs::read_to_string(output.path().join(options.file_name("locations"))).unwrap()
+    }
+
+    #[test]
+    fn literal_escaping() {
+        let mut buffer = String::new();
+        escape_literal(&mut buffer, "Piazza \"Dante\"\n\tC:\\ è\r");
+        assert_eq!(buffer, r#"Piazza \"Dante\"\n\tC:\\ è\r"#);
+    }
+
+    #[test]
+    fn iri_encoding() {
+        assert_eq!(encode_iri_segment("U_1-a.b~c"), "U_1-a.b~c");
+        assert_eq!(encode_iri_segment("U_1 a/è"), "U_1%20a%2F%C3%A8");
+        assert_eq!(
+            RdfOptions::default().resource_iri("Location", "U_1 a/è"),
+            "http://example.org/kdi/Location/U_1%20a%2F%C3%A8"
+        );
+    }
+
+    #[test]
+    fn escaped_triples() {
+        let location = location("Via \"Roma\"\nTrento");
+        let subject = "<http://example.org/kdi/Location/U_1%20a%2F%C3%A8>";
+
+        let turtle = write(RdfFormat::Turtle, &location);
+        assert!(turtle.contains(&format!("{} a kdi:Location", subject)));
+        assert!(turtle.contains(r#"kdi:name "Via \"Roma\"\nTrento""#));
+        assert!(turtle.contains(r#"kdi:latitude "46.07"^^xsd:double"#));
+
+        let n_triples = write(RdfFormat::NTriples, &location);
+        assert!(n_triples.lines().all(|line| line.ends_with(" .")));
+        assert!(n_triples.contains(&format!(
+            r#"{} <http://example.org/kdi/ontology/name> "Via \"Roma\"\nTrento" ."#,
+            subject
+        )));
+
+        let json_ld: Value = serde_json::from_str(&write(RdfFormat::JsonLd, &location)).unwrap();
+        assert_eq!(json_ld["@graph"][0]["name"], "Via \"Roma\"\nTrento");
+        assert_eq!(
+            json_ld["@graph"][0]["@id"],
+            "http://example.org/kdi/Location/U_1%20a%2F%C3%A8"
+        );
+    }
+
+    #[test]
+    fn json_ld_context_terms() {
+        let data = |file: &str| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("data")
+                .join(file)
+        };
+        let config = Config {
+            sources: vec![
+                Source::Gtfs {
+                    path: data("urban.zip"),
+                    tt: TT::Urban,
+                },
+                Source::Fare {
+                    path: data("urban_fare.zip"),
+                    tt: TT::Urban,
+                },
+                Source::Kml {
+                    path: data("car_sharing.kml"),
+                    layer: KmlLayer::CarSharing,
+                    prefix: None,
+                },
+                Source::BikeSharing {
+                    path: data("bikesharing_trento.json"),
+                    prefix: None,
+                },
+            ],
+            area: Default::default(),
+        };
+        let sources = Sources::read(&config).unwrap();
+        let groups = [Group::Common, Group::Core, Group::Contextual];
+        let alignment = Alignment::align(&sources, &groups, true).unwrap();
+        let output = tempfile::tempdir().unwrap();
+        let options = RdfOptions {
+            format: RdfFormat::JsonLd,
+            ..RdfOptions::default()
+        };
+        alignment.write_rdf(output.path(), &options).unwrap();
+
+        let context = options.json_ld_context();
+        let terms = context["@context"].as_object().unwrap();
+        let properties: HashMap<&str, HashSet<&str>> = classes()
+            .into_iter()
+            .map(|(class, properties)| (class, properties.iter().map(|p| p.name).collect()))
+            .collect();
+        let mut types = HashSet::new();
+        for entry in fs::read_dir(output.path()).unwrap() {
+            let path = entry.unwrap().path();
+            // Reports are plain `JSON`
+            if path.extension() != Some("jsonld".as_ref()) {
+                continue;
+            }
+            let document: Value =
+                serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            if path.ends_with("context.jsonld") {
+                assert_eq!(document, context);
+                continue;
+            }
+            for node in document["@graph"].as_array().unwrap() {
+                let class = node["@type"].as_str().unwrap();
+                assert!(terms.contains_key(class), "`{}` not in the context", class);
+                types.insert(class.to_string());
+                for name in node.as_object().unwrap().keys() {
+                    if name.starts_with('@') {
+                        continue;
+                    }
+                    assert!(terms.contains_key(name), "`{}` not in the context", name);
+                    assert!(
+                        properties[class].contains(name.as_str()),
+                        "`{}` is not a property of `{}`",
+                        name,
+                        class
+                    );
+                }
+            }
+        }
+        for (class, _) in classes() {
+            assert!(types.contains(class), "no `{}` node written", class);
+        }
+    }
+}
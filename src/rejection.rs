@@ -0,0 +1,143 @@
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::error::{KdiError, Record};
+
+/// Record dropped by a lenient alignment
+#[derive(Debug, Serialize)]
+pub struct Rejection {
+    pub entity: &'static str,
+    #[serde(flatten)]
+    pub record: Record,
+    pub reason: String,
+}
+
+/// Records rejected during an alignment.
+/// When not lenient every record error is returned as is
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct Rejections {
+    #[serde(skip)]
+    lenient: bool,
+    rejections: Vec<Rejection>,
+}
+
+impl Rejections {
+    pub fn new(lenient: bool) -> Rejections {
+        Rejections {
+            lenient,
+            rejections: Vec::new(),
+        }
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    pub fn rejections(&self) -> &[Rejection] {
+        &self.rejections
+    }
+
+    /// Append the rejections of `other`
+    pub fn extend(&mut self, other: Rejections) {
+        self.rejections.extend(other.rejections);
+    }
+
+    /// Unwrap the `entity` record `result`, rejecting it if the error is tied to a record
+    /// and the alignment is lenient
+    pub fn check<T>(
+        &mut self,
+        entity: &'static str,
+        result: Result<T, KdiError>,
+    ) -> Result<Option<T>, KdiError> {
+        let error = match result {
+            Ok(value) => return Ok(Some(value)),
+            Err(error) => error,
+        };
+
+        match error.record() {
+            Some(record) if self.lenient => {
+                warn!("Rejecting `{}` {}", entity, error);
+                self.rejections.push(Rejection {
+                    entity,
+                    record,
+                    reason: error.reason(),
+                });
+                Ok(None)
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// Number of rejected records of every entity
+    pub fn counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for rejection in &self.rejections {
+            *counts.entry(rejection.entity).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Log the number of rejected records of every entity
+    pub fn log_summary(&self) {
+        info!("Rejected {} records", self.rejections.len());
+        for (entity, count) in self.counts() {
+            info!("Rejected {} `{}` records", count, entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn invalid(id: &str) -> Result<u32, KdiError> {
+        Err(KdiError::InvalidField {
+            record: Record::new(Path::new("trips.txt"), Some(3), Some(id)),
+            field: "trip_headsign",
+            value: String::new(),
+        })
+    }
+
+    #[test]
+    fn lenient() {
+        let mut rejections = Rejections::new(true);
+        assert_eq!(rejections.check("Trip", Ok(1)).unwrap(), Some(1));
+        assert_eq!(rejections.check("Trip", invalid("T_1")).unwrap(), None);
+        assert_eq!(rejections.check("Trip", invalid("T_2")).unwrap(), None);
+        assert_eq!(rejections.check("Route", invalid("R_1")).unwrap(), None);
+
+        assert_eq!(rejections.rejections().len(), 3);
+        let rejection = &rejections.rejections()[0];
+        assert_eq!(rejection.entity, "Trip");
+        assert_eq!(rejection.record.id.as_deref(), Some("T_1"));
+        assert_eq!(rejection.record.index, Some(3));
+        assert_eq!(
+            rejections.counts(),
+            BTreeMap::from([("Route", 1), ("Trip", 2)])
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let mut rejections = Rejections::new(false);
+        assert!(matches!(
+            rejections.check("Trip", invalid("T_1")),
+            Err(KdiError::InvalidField { .. })
+        ));
+        assert!(rejections.rejections().is_empty());
+    }
+
+    #[test]
+    fn error_without_record() {
+        let mut rejections = Rejections::new(true);
+        let result: Result<u32, KdiError> = Err(KdiError::StepNotRun { step: "Core::Trip" });
+        assert!(matches!(
+            rejections.check("Trip", result),
+            Err(KdiError::StepNotRun { .. })
+        ));
+        assert!(rejections.counts().is_empty());
+    }
+}
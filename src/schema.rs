@@ -0,0 +1,552 @@
+use flate2::read::GzDecoder;
+use jsonschema::JSONSchema;
+use log::{debug, info, warn};
+use serde::de::{DeserializeSeed, SeqAccess, Visitor};
+use serde::Deserializer;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::alignment::snake_case;
+use crate::error::KdiError;
+use crate::rdf::{self, Datatype, Enumeration, Property, Range};
+
+/// `JSON Schema` dialect of the generated schemas
+const DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Aligned date times, e.g. `2021-09-13T00:00:00`, without a time zone
+const DATE_TIME_PATTERN: &str = r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}$";
+
+/// Entity files of the alignment, with the class of their records
+pub const ENTITIES: [(&str, &str); 12] = [
+    ("locations", "Location"),
+    ("calendar_exceptions", "CalendarException"),
+    ("calendars", "Calendar"),
+    ("agencies", "Agency"),
+    ("fare_rules", "FareRule"),
+    ("parking_stops", "ParkingStop"),
+    ("fares", "Fare"),
+    ("bike_sharing_stops", "BikeSharingStop"),
+    ("stop_times", "StopTime"),
+    ("trips", "Trip"),
+    ("routes", "Route"),
+    ("public_transport_stops", "PublicTransportStop"),
+];
+
+/// Schema of an output file
+pub struct Schema {
+    /// Output file name, without extensions
+    pub name: String,
+    pub schema: Value,
+    /// Whether the file is a list of records, validated one at a time
+    pub records: bool,
+}
+
+impl Schema {
+    /// Compile the schema of a document, or of a single record of a list of records
+    fn compile(&self) -> Result<JSONSchema, KdiError> {
+        // Records are validated one at a time against the schema of the array items
+        let schema = if self.records {
+            &self.schema["items"]
+        } else {
+            &self.schema
+        };
+        JSONSchema::compile(schema).map_err(|error| KdiError::InvalidSchema {
+            name: self.name.clone(),
+            reason: error.to_string(),
+        })
+    }
+}
+
+/// Schemas of every `JSON` output file
+pub fn schemas() -> Vec<Schema> {
+    let mut schemas = Vec::new();
+
+    // --- ENTITIES
+    for (name, class) in ENTITIES {
+        let properties = rdf::classes()
+            .into_iter()
+            .find(|(other, _)| *other == class)
+            .map_or(&[][..], |(_, properties)| properties);
+        schemas.push(Schema {
+            name: name.to_string(),
+            schema: records(class, entity(properties)),
+            records: true,
+        });
+    }
+
+    // --- CONTEXTUAL
+    for enumeration in rdf::ENUMERATIONS {
+        schemas.push(Schema {
+            name: snake_case(enumeration.class),
+            schema: json!({
+                "$schema": DIALECT,
+                "title": enumeration.class,
+                "type": "object",
+                "properties": {
+                    "value": {
+                        "type": "array",
+                        "items": { "enum": enumeration.variants },
+                    },
+                },
+                "required": ["value"],
+                "additionalProperties": false,
+            }),
+            records: false,
+        });
+    }
+
+    // --- SERVICE DATES
+    schemas.push(Schema {
+        name: "service_dates".to_string(),
+        schema: records(
+            "ServiceDate",
+            json!({
+                "type": "object",
+                "properties": {
+                    "calendar": { "type": "string" },
+                    "date": { "type": "string", "pattern": DATE_TIME_PATTERN },
+                },
+                "required": ["calendar", "date"],
+                "additionalProperties": false,
+            }),
+        ),
+        records: true,
+    });
+
+    // --- REJECTIONS
+    schemas.push(Schema {
+        name: "rejected".to_string(),
+        schema: records(
+            "Rejection",
+            json!({
+                "type": "object",
+                "properties": {
+                    "entity": { "type": "string" },
+                    "file": { "type": "string" },
+                    "index": { "type": ["integer", "null"], "minimum": 0 },
+                    "id": { "type": ["string", "null"] },
+                    "reason": { "type": "string" },
+                },
+                "required": ["entity", "file", "index", "id", "reason"],
+                "additionalProperties": false,
+            }),
+        ),
+        records: true,
+    });
+
+    // --- LOCATION ISSUES
+    schemas.push(Schema {
+        name: "location_issues".to_string(),
+        schema: records(
+            "LocationIssue",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file": { "type": "string" },
+                    "id": { "type": "string" },
+                    "issue": { "enum": ["Swapped", "Zero", "Outside"] },
+                    "latitude": { "type": "number" },
+                    "longitude": { "type": "number" },
+                },
+                "required": ["file", "id", "issue", "latitude", "longitude"],
+                "additionalProperties": false,
+            }),
+        ),
+        records: true,
+    });
+
+    schemas
+}
+
+/// Write the schema of every output file as `<name>.schema.json` under `output`
+pub fn write(output: &Path) -> Result<(), KdiError> {
+    for schema in schemas() {
+        let path = output.join(format!("{}.schema.json", schema.name));
+        info!("Writing `{}` file", path.display());
+        let file = File::create(&path).map_err(|source| KdiError::Io {
+            file: path.clone(),
+            source,
+        })?;
+        serde_json::to_writer_pretty(file, &schema.schema)
+            .map_err(|source| KdiError::Json { file: path, source })?;
+    }
+
+    Ok(())
+}
+
+/// Validate every `JSON` and `JSON Lines` file of the `input` alignment directory,
+/// compressed or not, against its schema.
+/// Chunk files are validated against the schema of their entity
+pub fn validate(input: &Path) -> Result<(), KdiError> {
+    let schemas = schemas();
+    let paths = alignment_files(input)?;
+
+    let mut files = 0;
+    let mut invalid = 0;
+    for path in paths {
+        let file = match FileName::parse(&path) {
+            Some(file) => file,
+            None => {
+                debug!("Skipping `{}`", path.display());
+                continue;
+            }
+        };
+        let schema = match schemas.iter().find(|schema| file.matches(&schema.name)) {
+            Some(schema) => schema,
+            None => {
+                debug!("Skipping `{}`, no schema", path.display());
+                continue;
+            }
+        };
+        info!("Validating `{}`", path.display());
+        files += 1;
+        invalid += validate_file(&path, &file, schema)?;
+    }
+
+    if files == 0 {
+        warn!("No alignment file found in `{}`", input.display());
+    }
+    if invalid > 0 {
+        return Err(KdiError::SchemaViolations {
+            file: input.to_path_buf(),
+            count: invalid,
+        });
+    }
+    info!("Validated {} files", files);
+
+    Ok(())
+}
+
+/// Paths of the files of the `input` directory, sorted by name
+pub(crate) fn alignment_files(input: &Path) -> Result<Vec<PathBuf>, KdiError> {
+    let io_error = |source| KdiError::Io {
+        file: input.to_path_buf(),
+        source,
+    };
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(input).map_err(io_error)? {
+        paths.push(entry.map_err(io_error)?.path());
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Output file name, split in its parts
+pub(crate) struct FileName<'a> {
+    stem: &'a str,
+    lines: bool,
+    compression: Option<&'a str>,
+}
+
+impl<'a> FileName<'a> {
+    /// Parts of `<stem>.<json|jsonl>[.<gz|zst>]` file names
+    pub(crate) fn parse(path: &'a Path) -> Option<FileName<'a>> {
+        let name = path.file_name()?.to_str()?;
+        let (name, compression) = match name.rsplit_once('.')? {
+            (name, extension @ ("gz" | "zst")) => (name, Some(extension)),
+            _ => (name, None),
+        };
+        let (stem, lines) = match name.rsplit_once('.')? {
+            (stem, "json") => (stem, false),
+            (stem, "jsonl") => (stem, true),
+            _ => return None,
+        };
+
+        Some(FileName {
+            stem,
+            lines,
+            compression,
+        })
+    }
+
+    /// Whether the file is the `name` file, or one of its `<name>_<n>` chunks
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        match self.stem.strip_prefix(name) {
+            Some("") => true,
+            Some(chunk) => chunk
+                .strip_prefix('_')
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())),
+            None => false,
+        }
+    }
+
+    /// Whether the file is one of the `<name>_<n>` chunks of the `name` file
+    pub(crate) fn is_chunk_of(&self, name: &str) -> bool {
+        self.matches(name) && self.stem != name
+    }
+
+    fn reader(&self, path: &Path) -> Result<Box<dyn BufRead>, KdiError> {
+        let io_error = |source| KdiError::Io {
+            file: path.to_path_buf(),
+            source,
+        };
+        let file = File::open(path).map_err(io_error)?;
+        let reader: Box<dyn Read> = match self.compression {
+            Some("gz") => Box::new(GzDecoder::new(file)),
+            Some(_) => Box::new(zstd::Decoder::new(file).map_err(io_error)?),
+            None => Box::new(file),
+        };
+
+        Ok(Box::new(BufReader::new(reader)))
+    }
+}
+
+/// Validate the `file` at `path`, returning the number of invalid documents or records
+fn validate_file(path: &Path, file: &FileName, schema: &Schema) -> Result<usize, KdiError> {
+    let compiled = schema.compile()?;
+    let mut index = 0;
+    let mut invalid = 0;
+    let mut check = |value: Value| {
+        if let Err(errors) = compiled.validate(&value) {
+            invalid += 1;
+            for error in errors {
+                warn!(
+                    "`{}` record #{}: {} at `{}`",
+                    path.display(),
+                    index,
+                    error,
+                    error.instance_path
+                );
+            }
+        }
+        index += 1;
+    };
+
+    if file.lines || schema.records {
+        read_records(path, file, &mut check)?;
+    } else {
+        let value =
+            serde_json::from_reader(file.reader(path)?).map_err(|source| KdiError::Json {
+                file: path.to_path_buf(),
+                source,
+            })?;
+        check(value);
+    }
+
+    Ok(invalid)
+}
+
+/// Pass the records of the `file` at `path`, a `JSON` array or `JSON Lines`, to `f` one at a time
+pub(crate) fn read_records(
+    path: &Path,
+    file: &FileName,
+    f: &mut impl FnMut(Value),
+) -> Result<(), KdiError> {
+    let json_error = |source| KdiError::Json {
+        file: path.to_path_buf(),
+        source,
+    };
+    let reader = file.reader(path)?;
+    if file.lines {
+        for line in reader.lines() {
+            let line = line.map_err(|source| KdiError::Io {
+                file: path.to_path_buf(),
+                source,
+            })?;
+            if !line.is_empty() {
+                f(serde_json::from_str(&line).map_err(json_error)?);
+            }
+        }
+    } else {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        Records(f)
+            .deserialize(&mut deserializer)
+            .and_then(|()| deserializer.end())
+            .map_err(json_error)?;
+    }
+
+    Ok(())
+}
+
+/// `JSON` array passed to the callback one record at a time
+struct Records<'a, F: FnMut(Value)>(&'a mut F);
+
+impl<'de, F: FnMut(Value)> DeserializeSeed<'de> for Records<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(Value)> Visitor<'de> for Records<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(record) = seq.next_element::<Value>()? {
+            (self.0)(record);
+        }
+        Ok(())
+    }
+}
+
+/// Array of `class` records
+fn records(class: &str, items: Value) -> Value {
+    json!({
+        "$schema": DIALECT,
+        "title": class,
+        "type": "array",
+        "items": items,
+    })
+}
+
+/// Record with an `id` and the `properties`
+fn entity(properties: &[Property]) -> Value {
+    let mut schemas = Map::new();
+    let mut required = vec![json!("id")];
+    schemas.insert("id".to_string(), json!({ "type": "string" }));
+    for property in properties {
+        let mut schema = if property.multiple {
+            list(&property.range)
+        } else {
+            range(&property.range)
+        };
+        // Missing optional values are serialized as `null`
+        if property.optional {
+            schema = json!({ "anyOf": [schema, { "type": "null" }] });
+        }
+        schemas.insert(property.name.to_string(), schema);
+        required.push(json!(property.name));
+    }
+
+    json!({
+        "type": "object",
+        "properties": schemas,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Serialized variants of `enumeration`.
+/// The `Other` variant of open enumerations is always serialized with its value, e.g. `Other(1500)`
+fn variants(enumeration: &Enumeration) -> Vec<&'static str> {
+    enumeration
+        .variants
+        .iter()
+        .copied()
+        .filter(|variant| !enumeration.open || *variant != "Other")
+        .collect()
+}
+
+/// Multiple values, serialized as a single comma separated string, e.g. `Bus,Train`
+fn list(range: &Range) -> Value {
+    let value = match range {
+        Range::Class(class) => match rdf::ENUMERATIONS.iter().find(|e| e.class == *class) {
+            Some(enumeration) => {
+                let mut variants = variants(enumeration).join("|");
+                if enumeration.open {
+                    variants.push_str(r"|Other\(\d+\)");
+                }
+                format!("(?:{})", variants)
+            }
+            None => "[^,]+".to_string(),
+        },
+        Range::Literal(_) => "[^,]+".to_string(),
+    };
+
+    json!({
+        "type": "string",
+        "pattern": format!("^(?:{}(?:,{})*)?$", value, value),
+    })
+}
+
+fn range(range: &Range) -> Value {
+    match range {
+        Range::Literal(Datatype::String) => json!({ "type": "string" }),
+        Range::Literal(Datatype::Integer) => json!({ "type": "integer" }),
+        Range::Literal(Datatype::Double) => json!({ "type": "number" }),
+        Range::Literal(Datatype::Boolean) => json!({ "type": "boolean" }),
+        Range::Literal(Datatype::DateTime) => {
+            json!({ "type": "string", "pattern": DATE_TIME_PATTERN })
+        }
+        Range::Literal(Datatype::AnyUri) => json!({ "type": "string", "format": "uri" }),
+        Range::Class(class) => match rdf::ENUMERATIONS.iter().find(|e| e.class == *class) {
+            // e.g. `Other(1500)`
+            Some(enumeration) if enumeration.open => json!({
+                "anyOf": [
+                    { "enum": variants(enumeration) },
+                    { "type": "string", "pattern": r"^Other\(\d+\)$" },
+                ],
+            }),
+            Some(enumeration) => json!({ "enum": enumeration.variants }),
+            // Reference to another entity, by ID
+            None => json!({ "type": "string" }),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::align::TT;
+    use crate::alignment::{Alignment, Group};
+    use crate::config::{Config, KmlLayer, Source};
+    use crate::output::{Format, OutputOptions};
+    use crate::source::Sources;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn schemas_compile() {
+        for schema in schemas() {
+            assert!(
+                schema.compile().is_ok(),
+                "`{}` does not compile",
+                schema.name
+            );
+        }
+    }
+
+    #[test]
+    fn aligned_files_validate() {
+        let data = |file: &str| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("data")
+                .join(file)
+        };
+        let config = Config {
+            sources: vec![
+                Source::Fare {
+                    path: data("urban_fare.zip"),
+                    tt: TT::Urban,
+                },
+                Source::Kml {
+                    path: data("centro_in_bici.kml"),
+                    layer: KmlLayer::CentroInBici,
+                    prefix: None,
+                },
+                Source::BikeSharing {
+                    path: data("bikesharing_trento.json"),
+                    prefix: None,
+                },
+            ],
+            area: Default::default(),
+        };
+        let sources = Sources::read(&config).unwrap();
+        let groups = [Group::Common, Group::Core, Group::Contextual];
+        let alignment = Alignment::align(&sources, &groups, true).unwrap();
+
+        for format in [Format::Json, Format::Jsonl] {
+            let output = tempfile::tempdir().unwrap();
+            let options = OutputOptions {
+                format,
+                chunk_size: NonZeroUsize::new(100_000),
+                ..OutputOptions::default()
+            };
+            alignment.write(output.path(), &options).unwrap();
+            let files = alignment_files(output.path()).unwrap();
+            assert!(files
+                .iter()
+                .any(|path| path.ends_with("fare_rules_0.json")
+                    || path.ends_with("fare_rules_0.jsonl")));
+            validate(output.path()).unwrap();
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::enums::KdiExceptionEnum;
+use crate::error::{KdiError, Record};
+use crate::structs::{KdiCalendar, KdiCalendarException, KdiTrip};
+
+/// Format of the aligned dates
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Active dates of every service, by calendar ID
+pub type ServiceDates = BTreeMap<String, BTreeSet<NaiveDate>>;
+
+/// Date a service is active on, a record of the `service_dates` file
+#[derive(Debug, Serialize)]
+pub struct ServiceDate<'a> {
+    pub calendar: &'a str,
+    pub date: String,
+}
+
+/// Expand the `calendars` and their `exceptions` into the dates every service is active on:
+/// the days of the week of its calendar from the start to the end date, plus the added
+/// and minus the removed exceptions.
+/// Services described only by exceptions are active on their added dates
+pub fn expand(
+    calendars: &[KdiCalendar],
+    exceptions: &[KdiCalendarException],
+) -> Result<ServiceDates, KdiError> {
+    let mut services = ServiceDates::new();
+    for calendar in calendars {
+        let (start, end) = range(calendar)?;
+        let dates = services.entry(calendar.id.clone()).or_default();
+        for date in start.iter_days().take_while(|date| *date <= end) {
+            if runs_on(calendar, date.weekday()) {
+                dates.insert(date);
+            }
+        }
+    }
+    for exception in exceptions {
+        let date = exception_date(exception)?;
+        let dates = services.entry(exception.calendar.clone()).or_default();
+        match exception.exception {
+            KdiExceptionEnum::Added => dates.insert(date),
+            KdiExceptionEnum::Removed => dates.remove(&date),
+        };
+    }
+
+    Ok(services)
+}
+
+/// Records of the `service_dates` file, by calendar and date
+pub fn records(services: &ServiceDates) -> impl Iterator<Item = ServiceDate<'_>> {
+    services.iter().flat_map(|(calendar, dates)| {
+        dates.iter().map(move |date| ServiceDate {
+            calendar,
+            date: date
+                .and_time(NaiveTime::MIN)
+                .format(DATE_TIME_FORMAT)
+                .to_string(),
+        })
+    })
+}
+
+/// Validate the services: exceptions outside of the range of their calendar,
+/// services that are never active and the `trips` referencing them are errors
+pub fn check(
+    calendars: &[KdiCalendar],
+    exceptions: &[KdiCalendarException],
+    trips: &[KdiTrip],
+) -> Result<(), KdiError> {
+    let mut errors = 0;
+
+    // --- EXCEPTIONS
+    let ranges = calendars
+        .iter()
+        .map(|calendar| Ok((calendar.id.as_str(), range(calendar)?)))
+        .collect::<Result<HashMap<&str, (NaiveDate, NaiveDate)>, KdiError>>()?;
+    for exception in exceptions {
+        let date = exception_date(exception)?;
+        // Services described only by exceptions have no range
+        if let Some((start, end)) = ranges.get(exception.calendar.as_str()) {
+            if date < *start || date > *end {
+                warn!(
+                    "{}: date `{}` outside of the `{}` calendar range, from `{}` to `{}`",
+                    exception_record(exception),
+                    date,
+                    exception.calendar,
+                    start,
+                    end
+                );
+                errors += 1;
+            }
+        }
+    }
+
+    // --- NEVER ACTIVE
+    let services = expand(calendars, exceptions)?;
+    for (calendar, _) in services.iter().filter(|(_, dates)| dates.is_empty()) {
+        warn!("Service `{}` is never active", calendar);
+        errors += 1;
+    }
+    for trip in trips {
+        if services.get(&trip.calendar).is_some_and(BTreeSet::is_empty) {
+            warn!(
+                "{}: never active service `{}`",
+                Record::new(Path::new("trips"), None, Some(&trip.id)),
+                trip.calendar
+            );
+            errors += 1;
+        }
+    }
+
+    info!(
+        "Services: {} services, {} active dates, {} errors",
+        services.len(),
+        services.values().map(BTreeSet::len).sum::<usize>(),
+        errors
+    );
+    if errors > 0 {
+        return Err(KdiError::CalendarViolations { count: errors });
+    }
+
+    Ok(())
+}
+
+/// Start and end dates of a calendar
+fn range(calendar: &KdiCalendar) -> Result<(NaiveDate, NaiveDate), KdiError> {
+    let record = || Record::new(Path::new("calendars"), None, Some(&calendar.id));
+    Ok((
+        date(&calendar.start_date, "startDate", record)?,
+        date(&calendar.end_date, "endDate", record)?,
+    ))
+}
+
+fn exception_date(exception: &KdiCalendarException) -> Result<NaiveDate, KdiError> {
+    date(&exception.date, "date", || exception_record(exception))
+}
+
+fn exception_record(exception: &KdiCalendarException) -> Record {
+    Record::new(Path::new("calendar_exceptions"), None, Some(&exception.id))
+}
+
+fn date(
+    value: &str,
+    field: &'static str,
+    record: impl Fn() -> Record,
+) -> Result<NaiveDate, KdiError> {
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .map(|date_time| date_time.date())
+        .map_err(|_| KdiError::InvalidField {
+            record: record(),
+            field,
+            value: value.to_string(),
+        })
+}
+
+/// Whether `calendar` runs on `weekday`
+fn runs_on(calendar: &KdiCalendar, weekday: Weekday) -> bool {
+    match weekday {
+        Weekday::Mon => calendar.monday,
+        Weekday::Tue => calendar.tuesday,
+        Weekday::Wed => calendar.wednesday,
+        Weekday::Thu => calendar.thursday,
+        Weekday::Fri => calendar.friday,
+        Weekday::Sat => calendar.saturday,
+        Weekday::Sun => calendar.sunday,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{KdiDirectionEnum, KdiSupportedEnum};
+
+    /// Calendar running on weekdays from Monday 2021-09-13 to Sunday 2021-09-19
+    fn weekdays(id: &str) -> KdiCalendar {
+        KdiCalendar {
+            id: id.to_string(),
+            start_date: "2021-09-13T00:00:00".to_string(),
+            end_date: "2021-09-19T00:00:00".to_string(),
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: false,
+            sunday: false,
+        }
+    }
+
+    fn exception(calendar: &str, date: &str, exception: KdiExceptionEnum) -> KdiCalendarException {
+        KdiCalendarException {
+            id: format!("{}_{}", calendar, date),
+            calendar: calendar.to_string(),
+            date: format!("{}T00:00:00", date),
+            exception,
+        }
+    }
+
+    fn trip(calendar: &str) -> KdiTrip<'static> {
+        KdiTrip {
+            id: format!("trip_{}", calendar),
+            route: "route".to_string(),
+            calendar: calendar.to_string(),
+            name: "",
+            direction: KdiDirectionEnum::Outbound,
+            weelchair: KdiSupportedEnum::Unknown,
+            bike: KdiSupportedEnum::Unknown,
+        }
+    }
+
+    /// Active dates of the `calendar` service, as `YYYY-MM-DD` strings
+    fn dates(services: &ServiceDates, calendar: &str) -> Vec<String> {
+        services[calendar]
+            .iter()
+            .map(NaiveDate::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn calendar_days() {
+        let services = expand(&[weekdays("weekdays")], &[]).unwrap();
+        assert_eq!(
+            dates(&services, "weekdays"),
+            [
+                "2021-09-13",
+                "2021-09-14",
+                "2021-09-15",
+                "2021-09-16",
+                "2021-09-17"
+            ]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_exceptions() {
+        let exceptions = [
+            exception("weekdays", "2021-09-15", KdiExceptionEnum::Removed),
+            exception("weekdays", "2021-09-18", KdiExceptionEnum::Added),
+            exception("holidays", "2021-12-25", KdiExceptionEnum::Added),
+        ];
+        let services = expand(&[weekdays("weekdays")], &exceptions).unwrap();
+        assert_eq!(
+            dates(&services, "weekdays"),
+            [
+                "2021-09-13",
+                "2021-09-14",
+                "2021-09-16",
+                "2021-09-17",
+                "2021-09-18"
+            ]
+        );
+        // Service described only by exceptions
+        assert_eq!(dates(&services, "holidays"), ["2021-12-25"]);
+        assert!(check(&[weekdays("weekdays")], &exceptions, &[trip("holidays")]).is_ok());
+    }
+
+    #[test]
+    fn exception_outside_of_the_calendar_range() {
+        let exceptions = [exception("weekdays", "2021-09-20", KdiExceptionEnum::Added)];
+        let services = expand(&[weekdays("weekdays")], &exceptions).unwrap();
+        assert!(dates(&services, "weekdays").contains(&"2021-09-20".to_string()));
+        assert!(matches!(
+            check(&[weekdays("weekdays")], &exceptions, &[]),
+            Err(KdiError::CalendarViolations { count: 1 })
+        ));
+    }
+
+    #[test]
+    fn never_active_service() {
+        let mut never = weekdays("never");
+        never.monday = false;
+        never.tuesday = false;
+        never.wednesday = false;
+        never.thursday = false;
+        never.friday = false;
+        let exceptions = [exception(
+            "weekdays",
+            "2021-09-13",
+            KdiExceptionEnum::Removed,
+        )];
+        let calendars = [weekdays("weekdays"), never];
+        let services = expand(&calendars, &exceptions).unwrap();
+        assert!(services["never"].is_empty());
+        // The service and the trip referencing it
+        assert!(matches!(
+            check(&calendars, &exceptions, &[trip("weekdays"), trip("never")]),
+            Err(KdiError::CalendarViolations { count: 2 })
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use gtfs_structures::Gtfs;
+use log::info;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::align::TT;
+use crate::config::{Config, KmlLayer, Source, BIKE_SHARING_DEFAULT_PREFIX};
+use crate::error::KdiError;
+use crate::geography::Area;
+use crate::json::BikeSharing;
+use crate::kml::Kml;
+
+/// A parsed `GTFS` feed
+pub struct GtfsSource {
+    pub path: PathBuf,
+    pub tt: TT,
+    pub gtfs: Gtfs,
+}
+
+/// A fare zip archive, opened on demand by every aligner that reads it
+#[derive(Debug)]
+pub struct FareSource {
+    pub path: PathBuf,
+    pub tt: TT,
+}
+
+impl FareSource {
+    pub fn archive(&self) -> Result<ZipArchive<File>, KdiError> {
+        let file = File::open(&self.path).map_err(|source| KdiError::Io {
+            file: self.path.clone(),
+            source,
+        })?;
+        ZipArchive::new(file).map_err(|source| KdiError::Zip {
+            file: self.path.clone(),
+            source,
+        })
+    }
+
+    /// Read the `name` entry of the archive
+    pub fn read_entry(
+        &self,
+        archive: &mut ZipArchive<File>,
+        name: &str,
+    ) -> Result<String, KdiError> {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .map_err(|source| KdiError::Zip {
+                file: self.path.join(name),
+                source,
+            })?
+            .read_to_string(&mut content)
+            .map_err(|source| KdiError::Io {
+                file: self.path.join(name),
+                source,
+            })?;
+
+        Ok(content)
+    }
+}
+
+/// A parsed `KML` layer with its resolved ID prefix
+pub struct KmlSource {
+    pub path: PathBuf,
+    pub layer: KmlLayer,
+    pub prefix: String,
+    pub kml: Kml,
+}
+
+/// A parsed bike sharing `JSON` file with its resolved ID prefix
+pub struct BikeSharingSource {
+    pub path: PathBuf,
+    pub prefix: String,
+    pub bike_sharing: Vec<BikeSharing>,
+}
+
+/// Every data source described by a [`Config`], read into memory
+#[derive(Default)]
+pub struct Sources {
+    pub gtfs: Vec<GtfsSource>,
+    pub fares: Vec<FareSource>,
+    pub kml: Vec<KmlSource>,
+    pub bike_sharing: Vec<BikeSharingSource>,
+    pub area: Area,
+}
+
+impl Sources {
+    /// Read all the sources of `config`
+    pub fn read(config: &Config) -> Result<Sources, KdiError> {
+        let mut sources = Sources {
+            area: config.area.clone(),
+            ..Sources::default()
+        };
+
+        for source in &config.sources {
+            match source {
+                // - Read `GTFS` file
+                Source::Gtfs { path, tt } => {
+                    info!("Reading `{}`", path.display());
+                    sources.gtfs.push(GtfsSource {
+                        path: path.clone(),
+                        tt: *tt,
+                        gtfs: Gtfs::new(&path.to_string_lossy()).map_err(|source| {
+                            KdiError::Gtfs {
+                                file: path.clone(),
+                                source,
+                            }
+                        })?,
+                    });
+                }
+                // - Read `FARE` file
+                Source::Fare { path, tt } => {
+                    info!("Reading `{}`", path.display());
+                    let source = FareSource {
+                        path: path.clone(),
+                        tt: *tt,
+                    };
+                    source.archive()?;
+                    sources.fares.push(source);
+                }
+                // - Read `KML` file
+                Source::Kml {
+                    path,
+                    layer,
+                    prefix,
+                } => {
+                    info!("Reading `{}`", path.display());
+                    sources.kml.push(KmlSource {
+                        path: path.clone(),
+                        layer: *layer,
+                        prefix: prefix
+                            .clone()
+                            .unwrap_or_else(|| layer.default_prefix().to_string()),
+                        kml: serde_xml_rs::from_str(&read_to_string(path)?).map_err(|source| {
+                            KdiError::Xml {
+                                file: path.clone(),
+                                source,
+                            }
+                        })?,
+                    });
+                }
+                // - Read `BIKESHARING` file
+                Source::BikeSharing { path, prefix } => {
+                    info!("Reading `{}`", path.display());
+                    sources.bike_sharing.push(BikeSharingSource {
+                        path: path.clone(),
+                        prefix: prefix
+                            .clone()
+                            .unwrap_or_else(|| BIKE_SHARING_DEFAULT_PREFIX.to_string()),
+                        bike_sharing: serde_json::from_str(&read_to_string(path)?).map_err(
+                            |source| KdiError::Json {
+                                file: path.clone(),
+                                source,
+                            },
+                        )?,
+                    });
+                }
+            }
+        }
+
+        Ok(sources)
+    }
+}
+
+fn read_to_string(path: &Path) -> Result<String, KdiError> {
+    fs::read_to_string(path).map_err(|source| KdiError::Io {
+        file: path.to_path_buf(),
+        source,
+    })
+}
@@ -0,0 +1,818 @@
+use log::{info, warn};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use std::collections::HashSet;
+use std::fmt::{Debug, Write as _};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{KdiError, Record};
+use crate::rejection::{Rejection, Rejections};
+use crate::structs::{
+    KdiAgency, KdiBikeSharingStop, KdiCalendar, KdiCalendarException, KdiFare, KdiFareRule,
+    KdiLocation, KdiParkingStop, KdiPublicTransportStop, KdiRoute, KdiStopTime, KdiTrip,
+};
+
+/// Column of a table
+#[derive(Debug)]
+pub struct Column {
+    pub name: &'static str,
+    /// `SQLite` type affinity
+    pub sql_type: &'static str,
+    pub nullable: bool,
+    /// Referenced table, whose primary key is the column value
+    pub references: Option<&'static str>,
+}
+
+impl Column {
+    const fn new(name: &'static str, sql_type: &'static str) -> Column {
+        Column {
+            name,
+            sql_type,
+            nullable: false,
+            references: None,
+        }
+    }
+
+    const fn nullable(mut self) -> Column {
+        self.nullable = true;
+        self
+    }
+
+    const fn references(mut self, table: &'static str) -> Column {
+        self.references = Some(table);
+        self
+    }
+}
+
+const fn text(name: &'static str) -> Column {
+    Column::new(name, "TEXT")
+}
+
+const fn integer(name: &'static str) -> Column {
+    Column::new(name, "INTEGER")
+}
+
+const fn real(name: &'static str) -> Column {
+    Column::new(name, "REAL")
+}
+
+/// Table of an entity
+#[derive(Debug)]
+pub struct Table {
+    pub name: &'static str,
+    /// Entity of the rows, as in the rejections
+    pub entity: &'static str,
+    pub columns: &'static [Column],
+    pub primary_key: &'static [&'static str],
+    /// Rows with an existing primary key are the same record and are skipped
+    pub ignore_duplicates: bool,
+}
+
+impl Table {
+    const fn new(name: &'static str, entity: &'static str, columns: &'static [Column]) -> Table {
+        Table {
+            name,
+            entity,
+            columns,
+            primary_key: &["id"],
+            ignore_duplicates: false,
+        }
+    }
+
+    fn column(&self, name: &str) -> Option<&'static Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// `CREATE TABLE` statement, with the foreign keys to the `tables` only
+    fn create(&self, tables: &[&Table]) -> String {
+        let mut sql = format!("CREATE TABLE {} (", self.name);
+        for column in self.columns {
+            let _ = write!(sql, "\n    {} {}", column.name, column.sql_type);
+            if !column.nullable {
+                sql.push_str(" NOT NULL");
+            }
+            sql.push(',');
+        }
+        if !self.primary_key.is_empty() {
+            let _ = write!(sql, "\n    PRIMARY KEY ({}),", self.primary_key.join(", "));
+        }
+        for column in self.columns {
+            if let Some(references) = column.references {
+                if tables.iter().any(|table| table.name == references) {
+                    let _ = write!(
+                        sql,
+                        "\n    FOREIGN KEY ({}) REFERENCES {} (id),",
+                        column.name, references
+                    );
+                }
+            }
+        }
+        sql.pop();
+        sql.push_str("\n)");
+        sql
+    }
+
+    fn insert(&self) -> String {
+        let names: Vec<&str> = self.columns.iter().map(|column| column.name).collect();
+        let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+        format!(
+            "INSERT{} INTO {} ({}) VALUES ({})",
+            if self.ignore_duplicates {
+                " OR IGNORE"
+            } else {
+                ""
+            },
+            self.name,
+            names.join(", "),
+            placeholders.join(", ")
+        )
+    }
+}
+
+// --- COMMON
+pub const LOCATIONS: Table = Table::new(
+    "locations",
+    "Common::Location",
+    &[
+        text("id"),
+        text("name"),
+        real("latitude"),
+        real("longitude"),
+    ],
+);
+pub const CALENDAR_EXCEPTIONS: Table = Table::new(
+    "calendar_exceptions",
+    "Common::CalendarException",
+    &[
+        text("id"),
+        text("calendar").references("calendars"),
+        text("date"),
+        text("exception"),
+    ],
+);
+pub const CALENDARS: Table = Table::new(
+    "calendars",
+    "Common::Calendar",
+    &[
+        text("id"),
+        text("start_date"),
+        text("end_date"),
+        integer("monday"),
+        integer("tuesday"),
+        integer("wednesday"),
+        integer("thursday"),
+        integer("friday"),
+        integer("saturday"),
+        integer("sunday"),
+    ],
+);
+pub const AGENCIES: Table = Table::new(
+    "agencies",
+    "Common::Agency",
+    &[
+        text("id"),
+        text("name"),
+        text("email"),
+        text("phone"),
+        text("url"),
+    ],
+);
+
+// --- CORE
+pub const FARE_RULES: Table = Table {
+    // The ID is made of every column, the same rule may be listed more than once
+    ignore_duplicates: true,
+    ..Table::new(
+        "fare_rules",
+        "Core::FareRule",
+        &[
+            text("id"),
+            text("fare").references("fares"),
+            text("route").nullable().references("routes"),
+            text("origin").nullable().references("locations"),
+            text("destination").nullable().references("locations"),
+            text("contains").nullable().references("locations"),
+        ],
+    )
+};
+pub const PARKING_STOPS: Table = Table::new(
+    "parking_stops",
+    "Core::ParkingStop",
+    &[
+        text("id"),
+        text("location").references("locations"),
+        text("type"),
+        text("address"),
+        integer("total_slots"),
+    ],
+);
+pub const FARES: Table = Table::new(
+    "fares",
+    "Core::Fare",
+    &[
+        text("id"),
+        real("price"),
+        text("currency"),
+        text("type"),
+        text("payment"),
+        integer("duration"),
+    ],
+);
+pub const BIKE_SHARING_STOPS: Table = Table::new(
+    "bike_sharing_stops",
+    "Core::BikeSharingStop",
+    &[
+        text("id"),
+        text("location").references("locations"),
+        text("type"),
+        text("address"),
+        integer("total_slots"),
+        integer("free_slots"),
+        integer("bikes"),
+    ],
+);
+pub const STOP_TIMES: Table = Table {
+    // A trip may pass through the same stop more than once, the ID is not unique
+    primary_key: &["trip", "sequence"],
+    ..Table::new(
+        "stop_times",
+        "Core::StopTime",
+        &[
+            text("id"),
+            text("trip").references("trips"),
+            text("stop").references("public_transport_stops"),
+            text("arrival").nullable(),
+            text("departure").nullable(),
+            integer("sequence"),
+        ],
+    )
+};
+pub const TRIPS: Table = Table::new(
+    "trips",
+    "Core::Trip",
+    &[
+        text("id"),
+        text("route").references("routes"),
+        text("calendar").references("calendars"),
+        text("name"),
+        text("direction"),
+        text("weelchair"),
+        text("bike"),
+    ],
+);
+pub const ROUTES: Table = Table::new(
+    "routes",
+    "Core::Route",
+    &[
+        text("id"),
+        text("agency").references("agencies"),
+        text("short_name"),
+        text("long_name"),
+        text("transport"),
+    ],
+);
+pub const PUBLIC_TRANSPORT_STOPS: Table = Table::new(
+    "public_transport_stops",
+    "Core::PublicTransportStop",
+    &[
+        text("id"),
+        text("location").references("locations"),
+        text("zone").nullable().references("locations"),
+        text("weelchair"),
+    ],
+);
+/// Transport modes of the public transport stops, one row per mode
+pub const PUBLIC_TRANSPORT_STOP_TYPES: Table = Table {
+    primary_key: &["stop", "type"],
+    ..Table::new(
+        "public_transport_stop_types",
+        "Core::PublicTransportStop",
+        &[
+            text("stop").references("public_transport_stops"),
+            text("type"),
+        ],
+    )
+};
+
+/// Rejected records of a lenient alignment
+const REJECTED: Table = Table {
+    primary_key: &[],
+    ..Table::new(
+        "rejected",
+        "",
+        &[
+            text("entity"),
+            text("file"),
+            integer("record").nullable(),
+            text("id").nullable(),
+            text("reason"),
+        ],
+    )
+};
+
+/// Tables of the `Common` entities
+pub const COMMON: [&Table; 4] = [&LOCATIONS, &CALENDAR_EXCEPTIONS, &CALENDARS, &AGENCIES];
+
+/// Tables of the `Core` entities
+pub const CORE: [&Table; 9] = [
+    &FARE_RULES,
+    &PARKING_STOPS,
+    &FARES,
+    &BIKE_SHARING_STOPS,
+    &STOP_TIMES,
+    &TRIPS,
+    &ROUTES,
+    &PUBLIC_TRANSPORT_STOPS,
+    &PUBLIC_TRANSPORT_STOP_TYPES,
+];
+
+/// Entity stored as a row of its table
+pub trait ToSqlite {
+    const TABLE: &'static Table;
+
+    /// Values of the row, in the order of the table columns
+    fn values(&self) -> Vec<Value>;
+}
+
+/// `SQLite` database written in a single transaction.
+/// Foreign keys are checked before committing, the file is removed if the transaction is not committed
+pub struct SqliteWriter {
+    path: PathBuf,
+    connection: Connection,
+    tables: Vec<&'static Table>,
+    committed: bool,
+}
+
+impl SqliteWriter {
+    /// Create the `name` database under `output`, with the `tables` only
+    pub fn create(
+        output: &Path,
+        name: &str,
+        tables: &[&'static Table],
+    ) -> Result<SqliteWriter, KdiError> {
+        let path = output.join(format!("{}.sqlite", name));
+        info!("Writing `{}` file", path.display());
+        match fs::remove_file(&path) {
+            Err(source) if source.kind() != io::ErrorKind::NotFound => {
+                return Err(KdiError::Io { file: path, source })
+            }
+            _ => {}
+        }
+        let connection = Connection::open(&path).map_err(|source| KdiError::Sqlite {
+            file: path.clone(),
+            source,
+        })?;
+        let writer = SqliteWriter {
+            path,
+            connection,
+            tables: tables.to_vec(),
+            committed: false,
+        };
+
+        let mut sql = String::from(
+            "PRAGMA journal_mode = OFF;\nPRAGMA synchronous = OFF;\nPRAGMA foreign_keys = ON;\nBEGIN;\nPRAGMA defer_foreign_keys = ON;\n",
+        );
+        for table in tables.iter().copied().chain([&REJECTED]) {
+            let _ = writeln!(sql, "{};", table.create(tables));
+        }
+        writer.execute_batch(&sql)?;
+
+        Ok(writer)
+    }
+
+    /// Insert every entity of `entities` in its table
+    pub fn write<'e, T: ToSqlite + 'e>(
+        &self,
+        entities: impl IntoIterator<Item = &'e T>,
+    ) -> Result<(), KdiError> {
+        for entity in entities {
+            self.insert(T::TABLE, entity.values())?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert the `values` row in `table`
+    pub fn insert(&self, table: &Table, values: Vec<Value>) -> Result<(), KdiError> {
+        self.connection
+            .prepare_cached(&table.insert())
+            .and_then(|mut statement| statement.execute(params_from_iter(values)))
+            .map_err(|source| self.error(source))?;
+
+        Ok(())
+    }
+
+    /// Insert the `rejections` in the `rejected` table
+    pub fn write_rejections(&self, rejections: &[Rejection]) -> Result<(), KdiError> {
+        for rejection in rejections {
+            self.insert(
+                &REJECTED,
+                vec![
+                    Value::Text(rejection.entity.to_string()),
+                    Value::Text(rejection.record.file.display().to_string()),
+                    rejection
+                        .record
+                        .index
+                        .map_or(Value::Null, |index| Value::Integer(index as i64)),
+                    rejection.record.id.clone().map_or(Value::Null, Value::Text),
+                    Value::Text(rejection.reason.clone()),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the foreign keys and commit.
+    /// Rows with a dangling reference are rejected, with the rows referencing them,
+    /// when `rejections` is lenient
+    pub fn finish(mut self, rejections: &mut Rejections) -> Result<(), KdiError> {
+        loop {
+            let violations = self.foreign_key_violations()?;
+            if violations.is_empty() {
+                break;
+            }
+            for (table, rowid, column) in violations {
+                let (id, value) = self
+                    .connection
+                    .query_row(
+                        &format!(
+                            "SELECT {}, {} FROM {} WHERE rowid = ?1",
+                            table
+                                .column("id")
+                                .map_or(table.primary_key[0], |id| id.name),
+                            column.name,
+                            table.name
+                        ),
+                        params![rowid],
+                        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+                    )
+                    .map_err(|source| self.error(source))?;
+                let error = KdiError::DanglingReference {
+                    record: Record::new(Path::new(table.name), None, Some(&id)),
+                    field: column.name,
+                    value,
+                };
+                rejections.check::<()>(table.entity, Err(error))?;
+                self.connection
+                    .execute(
+                        &format!("DELETE FROM {} WHERE rowid = ?1", table.name),
+                        params![rowid],
+                    )
+                    .map_err(|source| self.error(source))?;
+            }
+        }
+        self.write_rejections(rejections.rejections())?;
+        self.execute_batch("COMMIT;")?;
+        self.committed = true;
+
+        Ok(())
+    }
+
+    /// Rows with a dangling reference, with the referencing column
+    fn foreign_key_violations(
+        &self,
+    ) -> Result<Vec<(&'static Table, i64, &'static Column)>, KdiError> {
+        let mut statement = self
+            .connection
+            .prepare("PRAGMA foreign_key_check")
+            .map_err(|source| self.error(source))?;
+        let rows: Vec<(String, i64, i64)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(3)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(|source| self.error(source))?;
+
+        let mut violations = Vec::with_capacity(rows.len());
+        let mut rows_seen = HashSet::new();
+        for (name, rowid, foreign_key) in rows {
+            // A row is rejected once, for the first of its dangling references
+            if !rows_seen.insert((name.clone(), rowid)) {
+                continue;
+            }
+            let table = match self.tables.iter().find(|table| table.name == name) {
+                Some(table) => *table,
+                None => continue,
+            };
+            let from: String = self
+                .connection
+                .query_row(
+                    &format!(
+                        "SELECT \"from\" FROM pragma_foreign_key_list('{}') WHERE id = ?1",
+                        table.name
+                    ),
+                    params![foreign_key],
+                    |row| row.get(0),
+                )
+                .map_err(|source| self.error(source))?;
+            match table.column(&from) {
+                Some(column) => violations.push((table, rowid, column)),
+                None => warn!("Unknown foreign key column `{}.{}`", table.name, from),
+            }
+        }
+
+        Ok(violations)
+    }
+
+    fn execute_batch(&self, sql: &str) -> Result<(), KdiError> {
+        self.connection
+            .execute_batch(sql)
+            .map_err(|source| self.error(source))
+    }
+
+    fn error(&self, source: rusqlite::Error) -> KdiError {
+        KdiError::Sqlite {
+            file: self.path.clone(),
+            source,
+        }
+    }
+}
+
+impl Drop for SqliteWriter {
+    fn drop(&mut self) {
+        // Without a journal an interrupted transaction leaves a corrupted database
+        if !self.committed {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn string(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+/// Variant name of an enumeration value
+fn variant(value: &impl Debug) -> Value {
+    Value::Text(format!("{:?}", value))
+}
+
+fn count(value: usize) -> Value {
+    Value::Integer(value as i64)
+}
+
+impl ToSqlite for KdiLocation {
+    const TABLE: &'static Table = &LOCATIONS;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.name),
+            Value::Real(self.latitude),
+            Value::Real(self.longitude),
+        ]
+    }
+}
+
+impl ToSqlite for KdiCalendarException {
+    const TABLE: &'static Table = &CALENDAR_EXCEPTIONS;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.calendar),
+            string(&self.date),
+            variant(&self.exception),
+        ]
+    }
+}
+
+impl ToSqlite for KdiCalendar {
+    const TABLE: &'static Table = &CALENDARS;
+
+    fn values(&self) -> Vec<Value> {
+        let mut values = vec![
+            string(&self.id),
+            string(&self.start_date),
+            string(&self.end_date),
+        ];
+        values.extend(
+            [
+                self.monday,
+                self.tuesday,
+                self.wednesday,
+                self.thursday,
+                self.friday,
+                self.saturday,
+                self.sunday,
+            ]
+            .map(|day| Value::Integer(i64::from(day))),
+        );
+        values
+    }
+}
+
+impl ToSqlite for KdiAgency<'_> {
+    const TABLE: &'static Table = &AGENCIES;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(self.id),
+            string(self.name),
+            string(self.email),
+            string(self.phone),
+            string(self.url),
+        ]
+    }
+}
+
+impl ToSqlite for KdiFareRule {
+    const TABLE: &'static Table = &FARE_RULES;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.fare),
+            self.route.clone().map_or(Value::Null, Value::Text),
+            self.origin.clone().map_or(Value::Null, Value::Text),
+            self.destination.clone().map_or(Value::Null, Value::Text),
+            self.contains.clone().map_or(Value::Null, Value::Text),
+        ]
+    }
+}
+
+impl ToSqlite for KdiParkingStop {
+    const TABLE: &'static Table = &PARKING_STOPS;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.location),
+            variant(&self.ptype),
+            string(&self.address),
+            count(self.total_slots),
+        ]
+    }
+}
+
+impl ToSqlite for KdiFare {
+    const TABLE: &'static Table = &FARES;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            Value::Real(self.price),
+            variant(&self.currency),
+            variant(&self.ftype),
+            variant(&self.payment),
+            count(self.duration),
+        ]
+    }
+}
+
+impl ToSqlite for KdiBikeSharingStop {
+    const TABLE: &'static Table = &BIKE_SHARING_STOPS;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.location),
+            variant(&self.ptype),
+            string(&self.address),
+            count(self.total_slots),
+            count(self.free_slots),
+            count(self.bikes),
+        ]
+    }
+}
+
+impl ToSqlite for KdiStopTime {
+    const TABLE: &'static Table = &STOP_TIMES;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.trip),
+            string(&self.stop),
+            self.arrival.clone().map_or(Value::Null, Value::Text),
+            self.departure.clone().map_or(Value::Null, Value::Text),
+            count(self.sequence),
+        ]
+    }
+}
+
+impl ToSqlite for KdiTrip<'_> {
+    const TABLE: &'static Table = &TRIPS;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.route),
+            string(&self.calendar),
+            string(self.name),
+            variant(&self.direction),
+            variant(&self.weelchair),
+            variant(&self.bike),
+        ]
+    }
+}
+
+impl ToSqlite for KdiRoute<'_> {
+    const TABLE: &'static Table = &ROUTES;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(self.agency),
+            string(self.short_name),
+            string(self.long_name),
+            variant(&self.transport),
+        ]
+    }
+}
+
+impl ToSqlite for KdiPublicTransportStop {
+    const TABLE: &'static Table = &PUBLIC_TRANSPORT_STOPS;
+
+    fn values(&self) -> Vec<Value> {
+        vec![
+            string(&self.id),
+            string(&self.location),
+            self.zone.clone().map_or(Value::Null, Value::Text),
+            variant(&self.weelchair),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PARENTS: Table = Table::new("parents", "Test::Parent", &[text("id")]);
+    static CHILDREN: Table = Table::new(
+        "children",
+        "Test::Child",
+        &[text("id"), text("parent").references("parents")],
+    );
+    static GRANDCHILDREN: Table = Table::new(
+        "grandchildren",
+        "Test::Grandchild",
+        &[text("id"), text("child").references("children")],
+    );
+
+    /// Database with a child of an unknown parent, itself the child of a grandchild
+    fn database(output: &Path) -> SqliteWriter {
+        let db =
+            SqliteWriter::create(output, "test", &[&PARENTS, &CHILDREN, &GRANDCHILDREN]).unwrap();
+        let row = |values: &[&str]| {
+            values
+                .iter()
+                .map(|value| value.to_string().into())
+                .collect()
+        };
+        db.insert(&PARENTS, row(&["p"])).unwrap();
+        db.insert(&CHILDREN, row(&["c", "p"])).unwrap();
+        db.insert(&CHILDREN, row(&["orphan", "unknown"])).unwrap();
+        db.insert(&GRANDCHILDREN, row(&["g", "c"])).unwrap();
+        db.insert(&GRANDCHILDREN, row(&["orphan_child", "orphan"]))
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn dangling_references_rejected() {
+        let output = tempfile::tempdir().unwrap();
+        let mut rejections = Rejections::new(true);
+        database(output.path()).finish(&mut rejections).unwrap();
+
+        let rejected: Vec<(&str, Option<&str>)> = rejections
+            .rejections()
+            .iter()
+            .map(|rejection| (rejection.entity, rejection.record.id.as_deref()))
+            .collect();
+        assert_eq!(
+            rejected,
+            [
+                ("Test::Child", Some("orphan")),
+                ("Test::Grandchild", Some("orphan_child"))
+            ]
+        );
+
+        let connection = Connection::open(output.path().join("test.sqlite")).unwrap();
+        let count = |table: &str| -> i64 {
+            connection
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            (count("children"), count("grandchildren"), count("rejected")),
+            (1, 1, 2)
+        );
+    }
+
+    #[test]
+    fn dangling_references_abort() {
+        let output = tempfile::tempdir().unwrap();
+        let result = database(output.path()).finish(&mut Rejections::new(false));
+        assert!(matches!(
+            result,
+            Err(KdiError::DanglingReference {
+                field: "parent",
+                ..
+            })
+        ));
+        assert!(!output.path().join("test.sqlite").exists());
+    }
+}
@@ -0,0 +1,366 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use log::{debug, info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::{KdiError, Record};
+use crate::geography;
+use crate::structs::{KdiLocation, KdiPublicTransportStop, KdiStopTime};
+
+/// Speed above which the travel between two stops is deemed impossible, in km/h
+pub const MAX_SPEED: f64 = 150.0;
+
+/// Resolution of the `GTFS` times of the feeds, consecutive stops often share the same minute
+const TIME_RESOLUTION: i64 = 60;
+
+/// Consistency check of the stop times of every trip, in `sequence` order.
+///
+/// Decreasing times, departures before the arrival, duplicate sequences, first and last stops
+/// without a time and speeds over [`MAX_SPEED`] between the stops are errors.
+/// Gaps in the sequence are warnings: `GTFS` allows them, but they may hide missing stops
+pub struct StopTimeCheck {
+    /// `(latitude, longitude)` of every stop with a known location
+    coordinates: HashMap<String, (f64, f64)>,
+    /// Start of the service day of the aligned times
+    start: NaiveDateTime,
+    errors: usize,
+    warnings: usize,
+}
+
+/// Time, in seconds, and position of the last stop time of a trip with a time.
+/// The distance covered since is `None` when a stop along the way has no coordinate
+struct TimePoint<'a> {
+    stop_time: &'a KdiStopTime,
+    seconds: i64,
+    distance: Option<f64>,
+    coordinate: Option<(f64, f64)>,
+}
+
+impl StopTimeCheck {
+    /// Check the stop times of `stops`, located by `locations`
+    pub fn new(stops: &[KdiPublicTransportStop], locations: &[KdiLocation]) -> StopTimeCheck {
+        let mut skipped = 0;
+        let locations: HashMap<&str, &KdiLocation> = locations
+            .iter()
+            // Missing coordinates, already reported by the geographic check
+            .filter(|location| {
+                let missing = location.latitude == 0.0 || location.longitude == 0.0;
+                if missing {
+                    debug!("Skipping the zero coordinate of location `{}`", location.id);
+                    skipped += 1;
+                }
+                !missing
+            })
+            .map(|location| (location.id.as_str(), location))
+            .collect();
+        if skipped > 0 {
+            info!(
+                "Stop times: no speed check at the stops of {} locations with a zero coordinate",
+                skipped
+            );
+        }
+        let coordinates = stops
+            .iter()
+            .filter_map(|stop| {
+                let location = locations.get(stop.location.as_str())?;
+                Some((stop.id.clone(), (location.latitude, location.longitude)))
+            })
+            .collect();
+
+        StopTimeCheck {
+            coordinates,
+            start: NaiveDate::from_ymd_opt(0, 1, 1)
+                .unwrap_or(NaiveDate::MIN)
+                .and_time(NaiveTime::MIN),
+            errors: 0,
+            warnings: 0,
+        }
+    }
+
+    /// Check the `stop_times`, reporting every error before failing
+    pub fn check(mut self, stop_times: &[KdiStopTime]) -> Result<(), KdiError> {
+        let mut trips: BTreeMap<&str, Vec<&KdiStopTime>> = BTreeMap::new();
+        for stop_time in stop_times {
+            trips.entry(&stop_time.trip).or_default().push(stop_time);
+        }
+        for trip in trips.values_mut() {
+            trip.sort_by_key(|stop_time| stop_time.sequence);
+            self.trip(trip);
+        }
+
+        info!(
+            "Stop times: {} trips, {} errors, {} warnings",
+            trips.len(),
+            self.errors,
+            self.warnings
+        );
+        if self.errors > 0 {
+            return Err(KdiError::StopTimeViolations { count: self.errors });
+        }
+
+        Ok(())
+    }
+
+    /// Check the stop times of a trip, sorted by `sequence`
+    fn trip(&mut self, trip: &[&KdiStopTime]) {
+        // --- TIME POINTS
+        let last = trip.last().filter(|_| trip.len() > 1);
+        for (position, stop_time) in [("first", trip.first()), ("last", last)] {
+            match stop_time {
+                Some(stop_time) if stop_time.arrival.is_none() || stop_time.departure.is_none() => {
+                    self.error(stop_time, format!("missing time at the {} stop", position));
+                }
+                _ => {}
+            }
+        }
+
+        let mut previous: Option<&KdiStopTime> = None;
+        let mut time_point: Option<TimePoint> = None;
+        for &stop_time in trip {
+            // --- SEQUENCE
+            if let Some(previous) = previous {
+                if previous.sequence == stop_time.sequence {
+                    self.error(
+                        stop_time,
+                        format!("duplicate `sequence` {}", stop_time.sequence),
+                    );
+                } else if stop_time.sequence > previous.sequence + 1 {
+                    warn!(
+                        "{}: `sequence` gap from {} to {}, stop times may be missing",
+                        record(stop_time),
+                        previous.sequence,
+                        stop_time.sequence
+                    );
+                    self.warnings += 1;
+                }
+            }
+            previous = Some(stop_time);
+
+            // --- TIMES
+            let arrival = self.seconds(stop_time, "arrival", &stop_time.arrival);
+            let departure = self.seconds(stop_time, "departure", &stop_time.departure);
+            if let (Some(arrival), Some(departure)) = (arrival, departure) {
+                if departure < arrival {
+                    self.error(stop_time, "departure before the arrival".to_string());
+                }
+            }
+
+            // --- SPEED
+            let coordinate = self.coordinates.get(&stop_time.stop).copied();
+            let distance = match (&time_point, coordinate) {
+                (Some(point), Some(coordinate)) => point
+                    .distance
+                    .zip(point.coordinate)
+                    .map(|(distance, from)| distance + geography::distance(from, coordinate)),
+                _ => None,
+            };
+            if let (Some(point), Some(arrival)) = (&time_point, arrival.or(departure)) {
+                let travel = arrival - point.seconds;
+                if travel < 0 {
+                    self.error(
+                        stop_time,
+                        format!(
+                            "time earlier than at `{}` (`sequence` {})",
+                            point.stop_time.stop, point.stop_time.sequence
+                        ),
+                    );
+                } else if let Some(distance) = distance {
+                    let speed = distance / travel.max(TIME_RESOLUTION) as f64 * 3.6;
+                    if speed > MAX_SPEED {
+                        self.error(
+                            stop_time,
+                            format!(
+                                "{:.0} km/h from `{}` (`sequence` {}), over {} km/h",
+                                speed, point.stop_time.stop, point.stop_time.sequence, MAX_SPEED
+                            ),
+                        );
+                    }
+                }
+            }
+            time_point = match (departure.or(arrival), time_point) {
+                (Some(seconds), _) => Some(TimePoint {
+                    stop_time,
+                    seconds,
+                    distance: Some(0.0),
+                    coordinate,
+                }),
+                // Stop without a time, its distance counts towards the next time point
+                (None, Some(point)) => Some(TimePoint {
+                    distance,
+                    coordinate,
+                    ..point
+                }),
+                (None, None) => None,
+            };
+        }
+    }
+
+    /// Seconds since the start of the service day of a stop time `field`
+    fn seconds(
+        &mut self,
+        stop_time: &KdiStopTime,
+        field: &'static str,
+        value: &Option<String>,
+    ) -> Option<i64> {
+        let value = value.as_deref()?;
+        match NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+            Ok(time) => Some((time - self.start).num_seconds()),
+            Err(_) => {
+                warn!(
+                    "{}",
+                    KdiError::InvalidField {
+                        record: record(stop_time),
+                        field,
+                        value: value.to_string(),
+                    }
+                );
+                self.errors += 1;
+                None
+            }
+        }
+    }
+
+    fn error(&mut self, stop_time: &KdiStopTime, reason: String) {
+        warn!("{}: {}", record(stop_time), reason);
+        self.errors += 1;
+    }
+}
+
+fn record(stop_time: &KdiStopTime) -> Record {
+    Record::new(Path::new("stop_times"), None, Some(&stop_time.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::align::TT;
+    use crate::enums::KdiSupportedEnum;
+
+    /// `(stop, latitude, longitude)` of the stops of the trips
+    const STOPS: [(&str, f64, f64); 4] = [
+        ("trento", 46.0722, 11.1195),
+        ("gardolo", 46.1065, 11.1097),
+        ("rovereto", 45.8915, 11.0340),
+        ("unknown", 0.0, 0.0),
+    ];
+
+    fn check() -> StopTimeCheck {
+        let stop = |(id, _, _): &(&str, f64, f64)| KdiPublicTransportStop {
+            id: id.to_string(),
+            location: id.to_string(),
+            zone: None,
+            ptype: Vec::new(),
+            weelchair: KdiSupportedEnum::Unknown,
+            tt: TT::Urban,
+        };
+        let location = |(id, latitude, longitude): &(&str, f64, f64)| KdiLocation {
+            id: id.to_string(),
+            name: id.to_string(),
+            latitude: *latitude,
+            longitude: *longitude,
+        };
+        StopTimeCheck::new(
+            &STOPS.iter().map(stop).collect::<Vec<_>>(),
+            &STOPS.iter().map(location).collect::<Vec<_>>(),
+        )
+    }
+
+    /// Stop time at `stop`, arriving and departing at `time`, a `HH:MM` time of the service
+    /// day that may go past `24:00` as in `GTFS`
+    fn stop_time(sequence: usize, stop: &str, time: &str) -> KdiStopTime {
+        let (hours, minutes) = time.split_once(':').unwrap();
+        let (hours, minutes): (u32, u32) = (hours.parse().unwrap(), minutes.parse().unwrap());
+        let time = format!(
+            "0000-01-{:02}T{:02}:{:02}:00",
+            1 + hours / 24,
+            hours % 24,
+            minutes
+        );
+        KdiStopTime {
+            id: format!("trip_{}", sequence),
+            trip: "trip".to_string(),
+            stop: stop.to_string(),
+            arrival: Some(time.clone()),
+            departure: Some(time),
+            sequence,
+        }
+    }
+
+    /// Errors and warnings of the check of a trip
+    fn trip(stop_times: &[KdiStopTime]) -> (usize, usize) {
+        let mut check = check();
+        let trip: Vec<&KdiStopTime> = stop_times.iter().collect();
+        check.trip(&trip);
+        (check.errors, check.warnings)
+    }
+
+    #[test]
+    fn trip_past_midnight() {
+        let stop_times = [
+            stop_time(1, "trento", "23:50"),
+            stop_time(2, "gardolo", "24:02"),
+            stop_time(3, "trento", "25:10"),
+        ];
+        assert_eq!(trip(&stop_times), (0, 0));
+        assert!(check().check(&stop_times).is_ok());
+    }
+
+    #[test]
+    fn time_before_midnight_after_a_time_past_midnight() {
+        let stop_times = [
+            stop_time(1, "trento", "24:05"),
+            stop_time(2, "gardolo", "23:55"),
+        ];
+        assert_eq!(trip(&stop_times), (1, 0));
+    }
+
+    #[test]
+    fn duplicate_sequence() {
+        let stop_times = [
+            stop_time(1, "trento", "08:00"),
+            stop_time(2, "gardolo", "08:10"),
+            stop_time(2, "gardolo", "08:10"),
+            stop_time(3, "trento", "08:20"),
+        ];
+        assert_eq!(trip(&stop_times), (1, 0));
+        assert!(matches!(
+            check().check(&stop_times),
+            Err(KdiError::StopTimeViolations { count: 1 })
+        ));
+    }
+
+    #[test]
+    fn speed_over_the_limit() {
+        // About 20 km in 2 minutes
+        let stop_times = [
+            stop_time(1, "trento", "08:00"),
+            stop_time(2, "rovereto", "08:02"),
+        ];
+        assert_eq!(trip(&stop_times), (1, 0));
+        // About 20 km in 20 minutes
+        let stop_times = [
+            stop_time(1, "trento", "08:00"),
+            stop_time(2, "rovereto", "08:20"),
+        ];
+        assert_eq!(trip(&stop_times), (0, 0));
+    }
+
+    #[test]
+    fn zero_coordinate_skipped() {
+        let stop_times = [
+            stop_time(1, "trento", "08:00"),
+            stop_time(2, "unknown", "08:01"),
+        ];
+        assert_eq!(trip(&stop_times), (0, 0));
+    }
+
+    #[test]
+    fn sequence_gap() {
+        let stop_times = [
+            stop_time(1, "trento", "08:00"),
+            stop_time(3, "gardolo", "08:10"),
+        ];
+        assert_eq!(trip(&stop_times), (0, 1));
+        assert!(check().check(&stop_times).is_ok());
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize, Serializer};
 
-use super::enums::{
+use crate::align::TT;
+use crate::enums::{
     KdiCurrencyEnum, KdiDirectionEnum, KdiExceptionEnum, KdiFareEnum, KdiParkingStopEnum,
     KdiPaymentEnum, KdiSupportedEnum, KdiTransportEnum,
 };
@@ -49,6 +50,8 @@ pub struct KdiAgency<'a> {
     pub email: &'a str,
     pub phone: &'a str,
     pub url: &'a str,
+    #[serde(skip)]
+    pub timezone: &'a str,
 }
 
 // Core
@@ -59,17 +62,23 @@ pub struct KdiFareRule {
     pub id: String,
     #[serde(rename(deserialize = "FARE_ID"))]
     pub fare: String,
-    #[serde(rename(deserialize = "ORIGIN_ID"), default = "kdi_fare_rule_default")]
-    pub origin: String,
-    #[serde(
-        rename(deserialize = "DESTINATION_ID"),
-        default = "kdi_fare_rule_default"
-    )]
-    pub destination: String,
+    #[serde(rename(deserialize = "ROUTE_ID"), default)]
+    pub route: Option<String>,
+    #[serde(rename(deserialize = "ORIGIN_ID"), default)]
+    pub origin: Option<String>,
+    #[serde(rename(deserialize = "DESTINATION_ID"), default)]
+    pub destination: Option<String>,
+    #[serde(rename(deserialize = "CONTAINS_ID"), default)]
+    pub contains: Option<String>,
 }
 
-fn kdi_fare_rule_default() -> String {
-    "0001".to_string()
+impl KdiFareRule {
+    /// Origin, destination and contained zones of the rule
+    pub fn zones(&self) -> impl Iterator<Item = &String> {
+        [&self.origin, &self.destination, &self.contains]
+            .into_iter()
+            .flatten()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -122,9 +131,12 @@ pub struct KdiPublicTransportStop {
     pub id: String,
     pub location: String,
     pub zone: Option<String>,
-    #[serde(serialize_with = "ptype_serialization", rename(serialize="type"))]
+    #[serde(serialize_with = "ptype_serialization", rename(serialize = "type"))]
     pub ptype: Vec<KdiTransportEnum>,
     pub weelchair: KdiSupportedEnum,
+    /// Transport type of the `GTFS` feed of the stop
+    #[serde(skip)]
+    pub tt: TT,
 }
 
 fn ptype_serialization<S>(t: &[KdiTransportEnum], s: S) -> Result<S::Ok, S::Error>